@@ -0,0 +1,202 @@
+//! Huffman coding (type 0x20). After the header comes the tree: a byte holding (tree length / 2) - 1
+//! followed by the nodes, starting with the root. Every node holds a 6 bit offset to its children,
+//! which are at `(address & !1) + offset * 2 + 2` (child 0) and the byte after it (child 1), and two
+//! flags (bit 7 for child 0, bit 6 for child 1) that say whether a child is a data unit rather than
+//! another node. The bitstream follows the tree, as 32-bit little endian words read from the most
+//! significant bit down.
+//!
+//! Units are 4 or 8 bits wide (the low nibble of the header); 4-bit units are packed two to a byte,
+//! low nibble first.
+use { CompressionType, DecompressError, Header };
+
+/// The offset of the root node: right after the header and the tree length byte.
+const ROOT: usize = Header::LEN + 1;
+
+/// Decompresses all of `src` into dst, returning the decompressed length.
+pub fn decode(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let mut decoder = HuffmanDecoder::new(src)?;
+    if decoder.remaining() > dst.len() {
+        return Err(DecompressError::DstTooSmall);
+    }
+    decoder.read(dst)
+}
+
+/// Decompresses Huffman data a piece at a time, so that it can be decoded into a buffer much
+/// smaller than the decompressed data.
+pub struct HuffmanDecoder<'a> {
+    src: &'a [u8],
+    /// The offset of the first byte after the tree
+    tree_end: usize,
+    /// The offset of the next word of the bitstream
+    pos: usize,
+    word: u32,
+    bits_left: u32,
+    unit_bits: u8,
+    remaining: u32,
+}
+
+impl<'a> HuffmanDecoder<'a> {
+    pub fn new(src: &'a [u8]) -> Result<Self, DecompressError> {
+        let header = Header::parse_as(src, CompressionType::Huffman)?;
+        if header.param != 4 && header.param != 8 {
+            return Err(DecompressError::Corrupt);
+        }
+        if src.len() <= ROOT {
+            return Err(DecompressError::TooShort);
+        }
+        let tree_end = Header::LEN + (src[Header::LEN] as usize + 1) * 2;
+        Ok(HuffmanDecoder {
+            src,
+            tree_end,
+            pos: tree_end,
+            word: 0,
+            bits_left: 0,
+            unit_bits: header.param,
+            remaining: header.size,
+        })
+    }
+
+    /// The number of decompressed bytes that haven't been read yet.
+    pub fn remaining(&self) -> usize { self.remaining as usize }
+
+    fn next_bit(&mut self) -> Result<usize, DecompressError> {
+        if self.bits_left == 0 {
+            if self.pos + 4 > self.src.len() {
+                return Err(DecompressError::TooShort);
+            }
+            let s = &self.src[self.pos..self.pos + 4];
+            self.word = s[0] as u32 | ((s[1] as u32) << 8) | ((s[2] as u32) << 16) | ((s[3] as u32) << 24);
+            self.pos += 4;
+            self.bits_left = 32;
+        }
+        let bit = (self.word >> 31) as usize;
+        self.word <<= 1;
+        self.bits_left -= 1;
+        Ok(bit)
+    }
+
+    /// Walks the tree from the root to a data unit.
+    fn next_unit(&mut self) -> Result<u8, DecompressError> {
+        let mut addr = ROOT;
+        loop {
+            let node = self.src[addr];
+            let bit = self.next_bit()?;
+            let child = (addr & !1) + ((node & 0x3F) as usize) * 2 + 2 + bit;
+            if child >= self.tree_end || child >= self.src.len() {
+                return Err(DecompressError::Corrupt);
+            }
+            let is_data = node & (0x80 >> bit) != 0;
+            if is_data {
+                return Ok(self.src[child]);
+            }
+            addr = child;
+        }
+    }
+
+    /// Decompresses as much as fits into `out`, returning the number of bytes written. Returns 0
+    /// once all of the data has been read.
+    pub fn read(&mut self, out: &mut [u8]) -> Result<usize, DecompressError> {
+        let mut written = 0;
+        while written < out.len() && self.remaining != 0 {
+            out[written] = if self.unit_bits == 8 {
+                self.next_unit()?
+            } else {
+                let lo = self.next_unit()? & 0x0F;
+                let hi = self.next_unit()? & 0x0F;
+                lo | (hi << 4)
+            };
+            written += 1;
+            self.remaining -= 1;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tree with four data units, each with a 2 bit code: 00, 01, 10 and 11.
+    fn header_and_tree(unit_bits: u8, size: usize, units: [u8; 4]) -> Vec<u8> {
+        vec![
+            0x20 | unit_bits, size as u8, (size >> 8) as u8, (size >> 16) as u8,
+            // Tree length / 2 - 1, the root, and its two child nodes (at 6 and 7)
+            3, 0x00, 0xC0, 0xC1,
+            // The children of node 6 (at 8 and 9) and node 7 (at 10 and 11)
+            units[0], units[1], units[2], units[3],
+        ]
+    }
+
+    fn encode(unit_bits: u8, data: &[u8], units: [u8; 4]) -> Vec<u8> {
+        let mut symbols = Vec::new();
+        for &b in data {
+            if unit_bits == 8 {
+                symbols.push(b);
+            } else {
+                symbols.push(b & 0x0F);
+                symbols.push(b >> 4);
+            }
+        }
+        let mut out = header_and_tree(unit_bits, data.len(), units);
+        let (mut word, mut bits) = (0u32, 0);
+        for s in symbols {
+            let code = units.iter().position(|&u| u == s).unwrap() as u32;
+            word |= code << (30 - bits);
+            bits += 2;
+            if bits == 32 {
+                out.extend_from_slice(&[word as u8, (word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8]);
+                word = 0;
+                bits = 0;
+            }
+        }
+        if bits != 0 {
+            out.extend_from_slice(&[word as u8, (word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8]);
+        }
+        out
+    }
+
+    #[test]
+    fn round_trip_8bit() {
+        let units = [b'a', b'b', b'c', b'd'];
+        let data: Vec<u8> = (0..1000u32).map(|i| units[(i * 7 % 11 % 4) as usize]).collect();
+        let src = encode(8, &data, units);
+        let mut dst = vec![0; data.len()];
+        assert_eq!(decode(&src, &mut dst), Ok(data.len()));
+        assert_eq!(dst, data);
+    }
+
+    #[test]
+    fn round_trip_4bit() {
+        let units = [0x0, 0x3, 0x9, 0xF];
+        let data: Vec<u8> = (0..999u32)
+            .map(|i| units[(i % 4) as usize] | (units[(i * 5 % 3) as usize] << 4))
+            .collect();
+        let src = encode(4, &data, units);
+        let mut decoder = HuffmanDecoder::new(&src).unwrap();
+        let mut out = Vec::new();
+        let mut piece = [0u8; 9];
+        loop {
+            let n = decoder.read(&mut piece).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&piece[..n]);
+        }
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut dst = [0; 16];
+        let units = [1, 2, 3, 4];
+        assert_eq!(decode(&[0x28, 1, 0, 0], &mut dst), Err(DecompressError::TooShort));
+        assert_eq!(decode(&header_and_tree(5, 1, units), &mut dst), Err(DecompressError::Corrupt));
+        assert_eq!(decode(&header_and_tree(8, 17, units), &mut dst), Err(DecompressError::DstTooSmall));
+        // No bitstream after the tree
+        assert_eq!(decode(&header_and_tree(8, 1, units), &mut dst), Err(DecompressError::TooShort));
+        // The root points past the end of the tree
+        let mut src = encode(8, &[1, 2, 3], units);
+        src[5] = 0x3F;
+        assert_eq!(decode(&src, &mut dst), Err(DecompressError::Corrupt));
+    }
+}
@@ -0,0 +1,90 @@
+//! The compression formats understood by the GBA BIOS. Every compressed stream starts with a 32-bit
+//! header: bits 4-7 hold the compression type, bits 0-3 a type specific parameter, and bits 8-31
+//! the size of the decompressed data in bytes.
+//!
+//! Further documentation can be found here: https://problemkaputt.de/gbatek.htm#biosdecompressionfunctions
+//!
+//! Besides the header, this crate has pure Rust decoders for the LZ77, RLE and Huffman formats.
+//! They work on slices, and can decode in pieces into a small buffer (e.g. in IWRAM). Nothing in
+//! here touches hardware and it only depends on `core`, so the decoders also build and run their
+//! tests on a host, and can check compressed assets without an emulator. gbalib re-exports this
+//! crate as `gbalib::decompress`.
+#![cfg_attr(not(test), no_std)]
+
+pub mod lz77;
+pub mod rle;
+pub mod huffman;
+
+pub use self::lz77::Lz77Decoder;
+pub use self::rle::RleDecoder;
+pub use self::huffman::HuffmanDecoder;
+
+/// The compression types the BIOS can decode.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum CompressionType {
+    Lz77    = 0x10,
+    Huffman = 0x20,
+    Rle     = 0x30,
+    /// Difference filtered data; the parameter is 1 for 8-bit and 2 for 16-bit units.
+    Diff    = 0x80,
+}
+
+/// The reasons compressed data can be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DecompressError {
+    /// The source is too short to hold a header, or ends before the data it describes.
+    TooShort,
+    /// The header has a different (or unknown) compression type than the one requested.
+    WrongType,
+    /// The source or destination isn't aligned as the decoder requires.
+    Misaligned,
+    /// The destination can't hold the decompressed data.
+    DstTooSmall,
+    /// The compressed data is malformed.
+    Corrupt,
+}
+
+/// The header found at the start of every compressed stream.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Header {
+    pub kind: CompressionType,
+    /// The low 4 bits of the header. For Huffman data this is the size of a data unit in bits (4 or
+    /// 8), for Diff data the size of a unit in bytes (1 or 2); it is 0 otherwise.
+    pub param: u8,
+    /// The size of the decompressed data, in bytes.
+    pub size: u32,
+}
+
+impl Header {
+    /// The size of a header, in bytes.
+    pub const LEN: usize = 4;
+
+    pub fn parse(src: &[u8]) -> Result<Header, DecompressError> {
+        if src.len() < Header::LEN {
+            return Err(DecompressError::TooShort);
+        }
+        let kind = match src[0] & 0xF0 {
+            0x10 => CompressionType::Lz77,
+            0x20 => CompressionType::Huffman,
+            0x30 => CompressionType::Rle,
+            0x80 => CompressionType::Diff,
+            _ => return Err(DecompressError::WrongType),
+        };
+        Ok(Header {
+            kind,
+            param: src[0] & 0x0F,
+            size: src[1] as u32 | ((src[2] as u32) << 8) | ((src[3] as u32) << 16),
+        })
+    }
+
+    /// Parses the header and checks that it has the expected type.
+    pub fn parse_as(src: &[u8], kind: CompressionType) -> Result<Header, DecompressError> {
+        let header = Header::parse(src)?;
+        if header.kind != kind {
+            Err(DecompressError::WrongType)
+        } else {
+            Ok(header)
+        }
+    }
+}
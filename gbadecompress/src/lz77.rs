@@ -0,0 +1,251 @@
+//! LZ77 (type 0x10). After the header the data is a series of blocks of up to 8 units, each block
+//! starting with a flag byte whose bits (from the most significant down) say whether a unit is a
+//! literal byte (0) or a 2 byte reference into the data decoded so far (1): 4 bits of length - 3,
+//! followed by 12 bits of displacement - 1.
+use { CompressionType, DecompressError, Header };
+
+/// The furthest back a reference can reach, and so the size of an `Lz77Decoder`'s window.
+pub const WINDOW_LEN: usize = 0x1000;
+const WINDOW_MASK: usize = WINDOW_LEN - 1;
+
+#[inline(always)]
+fn byte(src: &[u8], pos: usize) -> Result<u8, DecompressError> {
+    match src.get(pos) {
+        Some(&b) => Ok(b),
+        None => Err(DecompressError::TooShort),
+    }
+}
+
+/// Decompresses all of `src` into dst, returning the decompressed length.
+pub fn decode(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = Header::parse_as(src, CompressionType::Lz77)?;
+    let size = header.size as usize;
+    if size > dst.len() {
+        return Err(DecompressError::DstTooSmall);
+    }
+
+    let mut pos = Header::LEN;
+    let mut out = 0;
+    while out < size {
+        let flags = byte(src, pos)?;
+        pos += 1;
+        for i in 0..8 {
+            if out == size {
+                break;
+            }
+            if flags & (0x80 >> i) == 0 {
+                dst[out] = byte(src, pos)?;
+                pos += 1;
+                out += 1;
+            } else {
+                let (b0, b1) = (byte(src, pos)?, byte(src, pos + 1)?);
+                pos += 2;
+                let len = (b0 >> 4) as usize + 3;
+                let disp = (((b0 & 0x0F) as usize) << 8 | b1 as usize) + 1;
+                if disp > out {
+                    return Err(DecompressError::Corrupt);
+                }
+                // References may overlap the bytes they produce, so this has to go byte by byte
+                for _ in 0..len {
+                    if out == size {
+                        break;
+                    }
+                    dst[out] = dst[out - disp];
+                    out += 1;
+                }
+            }
+        }
+    }
+    Ok(size)
+}
+
+/// Decompresses LZ77 data a piece at a time, so that it can be decoded into a buffer much smaller
+/// than the decompressed data. References can reach up to 4KB back, so the decoder keeps a copy of
+/// the last 4KB it produced in a window provided by the caller; that way it is small enough to
+/// live on the stack, while the window can be placed in EWRAM or reused between assets.
+pub struct Lz77Decoder<'a> {
+    src: &'a [u8],
+    pos: usize,
+    remaining: u32,
+    produced: usize,
+    flags: u8,
+    flag_bits: u8,
+    copy_len: u32,
+    copy_disp: usize,
+    window: &'a mut [u8; WINDOW_LEN],
+}
+
+impl<'a> Lz77Decoder<'a> {
+    /// Starts decoding `src`. The initial contents of `window` don't matter.
+    pub fn new(src: &'a [u8], window: &'a mut [u8; WINDOW_LEN]) -> Result<Self, DecompressError> {
+        let header = Header::parse_as(src, CompressionType::Lz77)?;
+        Ok(Lz77Decoder {
+            src,
+            pos: Header::LEN,
+            remaining: header.size,
+            produced: 0,
+            flags: 0,
+            flag_bits: 0,
+            copy_len: 0,
+            copy_disp: 0,
+            window,
+        })
+    }
+
+    /// The number of decompressed bytes that haven't been read yet.
+    pub fn remaining(&self) -> usize { self.remaining as usize }
+
+    #[inline(always)]
+    fn next_byte(&mut self) -> Result<u8, DecompressError> {
+        let b = byte(self.src, self.pos)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    #[inline(always)]
+    fn push(&mut self, b: u8) {
+        self.window[self.produced & WINDOW_MASK] = b;
+        self.produced += 1;
+        self.remaining -= 1;
+    }
+
+    /// Decompresses as much as fits into `out`, returning the number of bytes written. Returns 0
+    /// once all of the data has been read.
+    pub fn read(&mut self, out: &mut [u8]) -> Result<usize, DecompressError> {
+        let mut written = 0;
+        while written < out.len() && self.remaining != 0 {
+            let b = if self.copy_len != 0 {
+                self.copy_len -= 1;
+                self.window[(self.produced - self.copy_disp) & WINDOW_MASK]
+            } else {
+                if self.flag_bits == 0 {
+                    self.flags = self.next_byte()?;
+                    self.flag_bits = 8;
+                }
+                let is_reference = self.flags & 0x80 != 0;
+                self.flags <<= 1;
+                self.flag_bits -= 1;
+
+                if !is_reference {
+                    self.next_byte()?
+                } else {
+                    let (b0, b1) = (self.next_byte()?, self.next_byte()?);
+                    self.copy_disp = (((b0 & 0x0F) as usize) << 8 | b1 as usize) + 1;
+                    if self.copy_disp > self.produced {
+                        return Err(DecompressError::Corrupt);
+                    }
+                    self.copy_len = (b0 >> 4) as u32 + 2;
+                    self.window[(self.produced - self.copy_disp) & WINDOW_MASK]
+                }
+            };
+            self.push(b);
+            out[written] = b;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A greedy encoder, only good enough to produce test data with plenty of references.
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let size = data.len();
+        let mut out = vec![0x10, size as u8, (size >> 8) as u8, (size >> 16) as u8];
+        let mut i = 0;
+        while i < size {
+            let flag_pos = out.len();
+            out.push(0);
+            for bit in 0..8 {
+                if i == size {
+                    break;
+                }
+                let (mut best_len, mut best_disp) = (0, 0);
+                for disp in 1..(i.min(WINDOW_LEN) + 1) {
+                    let mut len = 0;
+                    while len < 18 && i + len < size && data[i + len] == data[i + len - disp] {
+                        len += 1;
+                    }
+                    if len > best_len {
+                        best_len = len;
+                        best_disp = disp;
+                    }
+                }
+                if best_len >= 3 {
+                    out[flag_pos] |= 0x80 >> bit;
+                    out.push((((best_len - 3) << 4) | ((best_disp - 1) >> 8)) as u8);
+                    out.push((best_disp - 1) as u8);
+                    i += best_len;
+                } else {
+                    out.push(data[i]);
+                    i += 1;
+                }
+            }
+        }
+        out
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..5000u32 {
+            data.push(if i % 7 < 4 { b'a' + (i % 3) as u8 } else { (i * 31 % 251) as u8 });
+        }
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = sample();
+        let src = encode(&data);
+        assert!(src.len() < data.len());
+        let mut dst = vec![0; data.len()];
+        assert_eq!(decode(&src, &mut dst), Ok(data.len()));
+        assert_eq!(dst, data);
+    }
+
+    #[test]
+    fn streaming_matches_decode() {
+        let data = sample();
+        let src = encode(&data);
+        let mut window = [0; WINDOW_LEN];
+        let mut decoder = Lz77Decoder::new(&src, &mut window).unwrap();
+        let mut out = Vec::new();
+        let mut piece = [0u8; 37];
+        loop {
+            let n = decoder.read(&mut piece).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&piece[..n]);
+        }
+        assert_eq!(decoder.remaining(), 0);
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn overlapping_reference() {
+        // 'a', then a reference 1 byte back repeating it 5 more times
+        let src = [0x10, 6, 0, 0, 0x40, b'a', 0x20, 0x00];
+        let mut dst = [0; 6];
+        assert_eq!(decode(&src, &mut dst), Ok(6));
+        assert_eq!(&dst, b"aaaaaa");
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut dst = [0; 16];
+        assert_eq!(decode(&[0x10, 4], &mut dst), Err(DecompressError::TooShort));
+        assert_eq!(decode(&[0x30, 4, 0, 0, 0, 1, 2, 3, 4], &mut dst), Err(DecompressError::WrongType));
+        assert_eq!(decode(&[0x10, 32, 0, 0, 0], &mut dst), Err(DecompressError::DstTooSmall));
+        // Truncated literal run
+        assert_eq!(decode(&[0x10, 4, 0, 0, 0x00, 1, 2], &mut dst), Err(DecompressError::TooShort));
+        // A reference before the start of the data
+        assert_eq!(decode(&[0x10, 4, 0, 0, 0x80, 0x00, 0x00], &mut dst), Err(DecompressError::Corrupt));
+        let src = [0x10, 4, 0, 0, 0x80, 0x00, 0x00];
+        let mut window = [0; WINDOW_LEN];
+        let mut decoder = Lz77Decoder::new(&src, &mut window).unwrap();
+        assert_eq!(decoder.read(&mut dst), Err(DecompressError::Corrupt));
+    }
+}
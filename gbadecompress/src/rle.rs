@@ -0,0 +1,157 @@
+//! Run length encoding (type 0x30). After the header the data is a series of runs, each starting
+//! with a flag byte: if bit 7 is set the next byte is repeated (flag & 0x7F) + 3 times, otherwise the
+//! next (flag & 0x7F) + 1 bytes are copied as they are.
+use { CompressionType, DecompressError, Header };
+
+/// Decompresses all of `src` into dst, returning the decompressed length.
+pub fn decode(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let mut decoder = RleDecoder::new(src)?;
+    if decoder.remaining() > dst.len() {
+        return Err(DecompressError::DstTooSmall);
+    }
+    decoder.read(dst)
+}
+
+/// Decompresses RLE data a piece at a time, so that it can be decoded into a buffer much smaller
+/// than the decompressed data.
+pub struct RleDecoder<'a> {
+    src: &'a [u8],
+    pos: usize,
+    remaining: u32,
+    /// Bytes left in the current run
+    run: u32,
+    /// The byte being repeated, if the current run is compressed
+    repeat: Option<u8>,
+}
+
+impl<'a> RleDecoder<'a> {
+    pub fn new(src: &'a [u8]) -> Result<Self, DecompressError> {
+        let header = Header::parse_as(src, CompressionType::Rle)?;
+        Ok(RleDecoder { src, pos: Header::LEN, remaining: header.size, run: 0, repeat: None })
+    }
+
+    /// The number of decompressed bytes that haven't been read yet.
+    pub fn remaining(&self) -> usize { self.remaining as usize }
+
+    #[inline(always)]
+    fn next_byte(&mut self) -> Result<u8, DecompressError> {
+        match self.src.get(self.pos) {
+            Some(&b) => {
+                self.pos += 1;
+                Ok(b)
+            },
+            None => Err(DecompressError::TooShort),
+        }
+    }
+
+    /// Decompresses as much as fits into `out`, returning the number of bytes written. Returns 0
+    /// once all of the data has been read.
+    pub fn read(&mut self, out: &mut [u8]) -> Result<usize, DecompressError> {
+        let mut written = 0;
+        while written < out.len() && self.remaining != 0 {
+            if self.run == 0 {
+                let flag = self.next_byte()?;
+                if flag & 0x80 != 0 {
+                    self.run = (flag & 0x7F) as u32 + 3;
+                    self.repeat = Some(self.next_byte()?);
+                } else {
+                    self.run = (flag & 0x7F) as u32 + 1;
+                    self.repeat = None;
+                }
+            }
+            out[written] = match self.repeat {
+                Some(b) => b,
+                None => self.next_byte()?,
+            };
+            written += 1;
+            self.run -= 1;
+            self.remaining -= 1;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let size = data.len();
+        let mut out = vec![0x30, size as u8, (size >> 8) as u8, (size >> 16) as u8];
+        let mut i = 0;
+        let mut literals: Vec<u8> = Vec::new();
+        while i < size {
+            let mut run = 1;
+            while run < 130 && i + run < size && data[i + run] == data[i] {
+                run += 1;
+            }
+            if run >= 3 {
+                if !literals.is_empty() {
+                    out.push((literals.len() - 1) as u8);
+                    out.append(&mut literals);
+                }
+                out.push(0x80 | (run - 3) as u8);
+                out.push(data[i]);
+                i += run;
+            } else {
+                literals.push(data[i]);
+                i += 1;
+                if literals.len() == 128 {
+                    out.push(127);
+                    out.append(&mut literals);
+                }
+            }
+        }
+        if !literals.is_empty() {
+            out.push((literals.len() - 1) as u8);
+            out.append(&mut literals);
+        }
+        out
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..3000u32 {
+            data.push(if (i / 50) % 2 == 0 { 7 } else { (i * 13 % 256) as u8 });
+        }
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = sample();
+        let src = encode(&data);
+        let mut dst = vec![0; data.len()];
+        assert_eq!(decode(&src, &mut dst), Ok(data.len()));
+        assert_eq!(dst, data);
+    }
+
+    #[test]
+    fn streaming_matches_decode() {
+        let data = sample();
+        let src = encode(&data);
+        let mut decoder = RleDecoder::new(&src).unwrap();
+        let mut out = Vec::new();
+        let mut piece = [0u8; 5];
+        loop {
+            let n = decoder.read(&mut piece).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&piece[..n]);
+        }
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let mut dst = [0; 16];
+        assert_eq!(decode(&[0x30, 1, 0], &mut dst), Err(DecompressError::TooShort));
+        assert_eq!(decode(&[0x10, 1, 0, 0, 0x00, 1], &mut dst), Err(DecompressError::WrongType));
+        assert_eq!(decode(&[0x30, 17, 0, 0, 0x8E, 1], &mut dst), Err(DecompressError::DstTooSmall));
+        // A repeated run with no byte to repeat
+        assert_eq!(decode(&[0x30, 3, 0, 0, 0x80], &mut dst), Err(DecompressError::TooShort));
+        // Fewer literals than the flag promises
+        assert_eq!(decode(&[0x30, 4, 0, 0, 0x03, 1, 2], &mut dst), Err(DecompressError::TooShort));
+    }
+}
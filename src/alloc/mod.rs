@@ -10,6 +10,7 @@ use core::{ mem, iter };
 use core::option::*;
 
 use ptr::Ptr;
+use interrupt;
 
 
 // first 8 bytes are used to point to free / used lists
@@ -345,35 +346,44 @@ impl iter::Iterator for ChunkIterator {
     }
 }
 
+// The free and used lists are shared by everything that allocates, including interrupt handlers,
+// so they are only ever touched inside a critical section.
+
 pub unsafe fn alloc_initialize() {
-    let mut free_head: Ptr<Ptr<Chunk>> = Chunk::get_free_head();
-    (*free_head).num = RAM_START;
-    (**free_head).initialize();
-    (**free_head).len = RAM_END - RAM_START - mem::size_of::<Chunk>() as u32;
+    interrupt::free(|_| {
+        let mut free_head: Ptr<Ptr<Chunk>> = Chunk::get_free_head();
+        (*free_head).num = RAM_START;
+        (**free_head).initialize();
+        (**free_head).len = RAM_END - RAM_START - mem::size_of::<Chunk>() as u32;
+    })
 }
 
 pub unsafe fn alloc<T: Sized>(len: u32) -> Ptr<T> {
     let len = mem::size_of::<T>() as u32 * len;
-    let head: Ptr<Ptr<Chunk>> = Chunk::get_free_head();
-    let mut current = (*head).transmute::<Chunk>();
-    while ! current.is_null() {
-        let result: Ptr<Chunk> = current.try_alloc(len);
-        if result.is_null() {
-            current = (*current).next;
-            continue
+    interrupt::free(|_| {
+        let head: Ptr<Ptr<Chunk>> = Chunk::get_free_head();
+        let mut current = (*head).transmute::<Chunk>();
+        while ! current.is_null() {
+            let result: Ptr<Chunk> = current.try_alloc(len);
+            if result.is_null() {
+                current = (*current).next;
+                continue
+            }
+            return (*result).get_data_ptr::<T>();
         }
-        return (*result).get_data_ptr::<T>();
-    }
-    Ptr::<T>::null()
+        Ptr::<T>::null()
+    })
 }
 
 pub unsafe fn free<T: Sized>(ptr: &mut Ptr<T>) {
     if ptr.is_null() {
         return;
     } else {
-        let mut chunk: Ptr<Chunk> = Ptr::<Chunk>::from_u32(ptr.num - (mem::size_of::<Chunk>() as u32));
-        (*chunk).remove_from_used_list();
-        Chunk::append_to_free(chunk);
+        interrupt::free(|_| {
+            let mut chunk: Ptr<Chunk> = Ptr::<Chunk>::from_u32(ptr.num - (mem::size_of::<Chunk>() as u32));
+            (*chunk).remove_from_used_list();
+            Chunk::append_to_free(chunk);
+        });
         ptr.num = 0;
     }
 }
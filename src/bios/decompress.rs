@@ -0,0 +1,102 @@
+//! Wrappers around the BIOS decompression functions. The WRAM variants write a byte at a time and
+//! are slightly faster; the VRAM variants only write 16 bits at a time, so they are the ones to use
+//! when decoding straight into VRAM (e.g. into a `TILE_MEMORY` charblock).
+//!
+//! Every wrapper checks the header before calling the BIOS, and returns the decompressed length in
+//! bytes.
+use decompress::{ CompressionType, DecompressError, Header };
+
+/// Checks that `src` holds a header of type `kind`, that src is word aligned (the BIOS reads the
+/// header as a word), that dst is aligned to `dst_align` bytes and that the decompressed data
+/// (rounded up to a multiple of `dst_align`) fits in dst.
+fn check(src: &[u8], dst: &[u8], kind: CompressionType, dst_align: u32) -> Result<Header, DecompressError> {
+    let header = Header::parse_as(src, kind)?;
+    if src.as_ptr() as u32 & 3 != 0 || dst.as_ptr() as u32 & (dst_align - 1) != 0 {
+        return Err(DecompressError::Misaligned);
+    }
+    let padded = (header.size + dst_align - 1) & !(dst_align - 1);
+    if padded as usize > dst.len() {
+        return Err(DecompressError::DstTooSmall);
+    }
+    Ok(header)
+}
+
+macro_rules! swi_decompress {
+    ($swi:expr, $src:expr, $dst:expr) => {
+        asm!(concat!("swi ", $swi)
+        :
+        : "{r0}"($src.as_ptr()), "{r1}"($dst.as_mut_ptr())
+        : "r0", "r1", "r2", "r3", "memory"
+        : "volatile")
+    }
+}
+
+/// LZ77UnCompWram (SWI 0x11)
+pub fn decompress_lz77_wram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check(src, dst, CompressionType::Lz77, 1)?;
+    unsafe { swi_decompress!("0x11", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// LZ77UnCompVram (SWI 0x12). dst must be halfword aligned.
+pub fn decompress_lz77_vram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check(src, dst, CompressionType::Lz77, 2)?;
+    unsafe { swi_decompress!("0x12", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// HuffUnComp (SWI 0x13). The data is written 32 bits at a time, so dst must be word aligned, and
+/// this is safe to use on VRAM.
+pub fn decompress_huffman(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check(src, dst, CompressionType::Huffman, 4)?;
+    if header.param != 4 && header.param != 8 {
+        return Err(DecompressError::Corrupt);
+    }
+    unsafe { swi_decompress!("0x13", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// RLUnCompWram (SWI 0x14)
+pub fn decompress_rle_wram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check(src, dst, CompressionType::Rle, 1)?;
+    unsafe { swi_decompress!("0x14", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// RLUnCompVram (SWI 0x15). dst must be halfword aligned.
+pub fn decompress_rle_vram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check(src, dst, CompressionType::Rle, 2)?;
+    unsafe { swi_decompress!("0x15", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// Checks the header of Diff filtered data, including its unit size.
+fn check_diff(src: &[u8], dst: &[u8], unit: u8, dst_align: u32) -> Result<Header, DecompressError> {
+    let header = check(src, dst, CompressionType::Diff, dst_align)?;
+    if header.param != unit {
+        return Err(DecompressError::WrongType);
+    }
+    Ok(header)
+}
+
+/// Diff8bitUnFilterWram (SWI 0x16)
+pub fn decompress_diff8_wram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check_diff(src, dst, 1, 1)?;
+    unsafe { swi_decompress!("0x16", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// Diff8bitUnFilterVram (SWI 0x17). dst must be halfword aligned.
+pub fn decompress_diff8_vram(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check_diff(src, dst, 1, 2)?;
+    unsafe { swi_decompress!("0x17", src, dst) }
+    Ok(header.size as usize)
+}
+
+/// Diff16bitUnFilter (SWI 0x18). Writes 16 bits at a time, so dst must be halfword aligned, and
+/// this is safe to use on VRAM.
+pub fn decompress_diff16(src: &[u8], dst: &mut [u8]) -> Result<usize, DecompressError> {
+    let header = check_diff(src, dst, 2, 2)?;
+    unsafe { swi_decompress!("0x18", src, dst) }
+    Ok(header.size as usize)
+}
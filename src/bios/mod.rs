@@ -0,0 +1,423 @@
+//! Typed wrappers around the GBA BIOS software interrupts. The crate is compiled as Thumb code (the
+//! `thumbv4t-none-eabi` target in gba.json), so the SWI numbers are the Thumb forms (`swi 0x0B`
+//! rather than `swi 0x0B0000`).
+use core::mem::size_of;
+use interrupt::Irq;
+
+mod decompress;
+pub use self::decompress::*;
+
+/// Whether a CpuSet / CpuFastSet call copies a whole source region or repeats a single source
+/// value over the destination.
+#[derive(Copy, Clone)]
+#[repr(u32)]
+pub enum SetMode {
+    Copy    = 0x0000_0000,
+    Fill    = 0x0100_0000,
+}
+
+/// The size of a single unit transferred by CpuSet.
+#[derive(Copy, Clone)]
+#[repr(u32)]
+pub enum UnitSize {
+    _16bit  = 0x0000_0000,
+    _32bit  = 0x0400_0000,
+}
+
+impl UnitSize {
+    pub fn bytes(self) -> u32 {
+        match self {
+            UnitSize::_16bit => 2,
+            UnitSize::_32bit => 4,
+        }
+    }
+}
+
+/// The reasons a slice based CpuSet / CpuFastSet call can be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CpuSetError {
+    /// The source and destination slices have different lengths.
+    LengthMismatch,
+    /// The source or destination isn't aligned to the unit size (or to a word for CpuFastSet).
+    Misaligned,
+    /// The length in bytes isn't a multiple of the unit size (or of 32 bytes for CpuFastSet).
+    BadLength,
+    /// The transfer has more units than fit in the 21-bit count field.
+    TooLong,
+}
+
+/// The largest unit count CpuSet and CpuFastSet accept.
+const MAX_UNITS: u32 = 0x001F_FFFF;
+
+/// CpuSet (SWI 0x0B): copies `units` units from src to dst, or fills `units` units at dst with the
+/// unit at src. Both addresses must be aligned to the unit size.
+pub unsafe fn cpu_set(src: u32, dst: u32, units: u32, mode: SetMode, unit: UnitSize) {
+    let control = (units & MAX_UNITS) | mode as u32 | unit as u32;
+    asm!("swi 0x0B"
+    :
+    : "{r0}"(src), "{r1}"(dst), "{r2}"(control)
+    : "r0", "r1", "r2", "r3", "memory"
+    : "volatile");
+}
+
+/// CpuFastSet (SWI 0x0C): like `cpu_set` with 32-bit units, but transfers 8 words at a time. Both
+/// addresses must be word aligned, and `words` is rounded up to a multiple of 8 by the BIOS.
+pub unsafe fn cpu_fast_set(src: u32, dst: u32, words: u32, mode: SetMode) {
+    let control = (words & MAX_UNITS) | mode as u32;
+    asm!("swi 0x0C"
+    :
+    : "{r0}"(src), "{r1}"(dst), "{r2}"(control)
+    : "r0", "r1", "r2", "r3", "memory"
+    : "volatile");
+}
+
+/// Checks that `addr` and `bytes` describe a region made of whole, aligned units of `align` bytes
+/// (where `len_multiple` is the granularity the length has to have), returning the unit count.
+fn check_region(addr: u32, bytes: u32, align: u32, len_multiple: u32) -> Result<u32, CpuSetError> {
+    if addr & (align - 1) != 0 {
+        Err(CpuSetError::Misaligned)
+    } else if bytes % len_multiple != 0 {
+        Err(CpuSetError::BadLength)
+    } else if bytes / align > MAX_UNITS {
+        Err(CpuSetError::TooLong)
+    } else {
+        Ok(bytes / align)
+    }
+}
+
+/// Copies src into dst with CpuSet, `unit` bytes at a time.
+pub fn copy_slice<T: Copy>(src: &[T], dst: &mut [T], unit: UnitSize) -> Result<(), CpuSetError> {
+    if src.len() != dst.len() {
+        return Err(CpuSetError::LengthMismatch);
+    }
+    let bytes = (src.len() * size_of::<T>()) as u32;
+    let (s, d) = (src.as_ptr() as u32, dst.as_mut_ptr() as u32);
+    check_region(s, bytes, unit.bytes(), unit.bytes())?;
+    let units = check_region(d, bytes, unit.bytes(), unit.bytes())?;
+    unsafe { cpu_set(s, d, units, SetMode::Copy, unit) }
+    Ok(())
+}
+
+/// The element types `fill_slice` can fill: plain integers of a CpuSet unit's width, for which
+/// every bit pattern is a valid value.
+pub trait SetUnit: Copy + sealed::Sealed {
+    const UNIT: UnitSize;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
+
+impl SetUnit for u16 { const UNIT: UnitSize = UnitSize::_16bit; }
+impl SetUnit for u32 { const UNIT: UnitSize = UnitSize::_32bit; }
+
+/// Fills dst with `val` using CpuSet, in units of the element size.
+pub fn fill_slice<T: SetUnit>(val: T, dst: &mut [T]) -> Result<(), CpuSetError> {
+    let unit = T::UNIT;
+    let bytes = (dst.len() * size_of::<T>()) as u32;
+    let d = dst.as_mut_ptr() as u32;
+    let units = check_region(d, bytes, unit.bytes(), unit.bytes())?;
+    // The BIOS reads the fill value from memory, at the same width as the units
+    unsafe { cpu_set(&val as * const T as u32, d, units, SetMode::Fill, unit) }
+    Ok(())
+}
+
+/// Copies src into dst with CpuFastSet. Both slices must be word aligned and a multiple of 32
+/// bytes long.
+pub fn fast_copy_slice<T: Copy>(src: &[T], dst: &mut [T]) -> Result<(), CpuSetError> {
+    if src.len() != dst.len() {
+        return Err(CpuSetError::LengthMismatch);
+    }
+    let bytes = (src.len() * size_of::<T>()) as u32;
+    let (s, d) = (src.as_ptr() as u32, dst.as_mut_ptr() as u32);
+    check_region(s, bytes, 4, 32)?;
+    let words = check_region(d, bytes, 4, 32)?;
+    unsafe { cpu_fast_set(s, d, words, SetMode::Copy) }
+    Ok(())
+}
+
+/// Fills dst with the word `val` using CpuFastSet. dst must be word aligned and a multiple of 32
+/// bytes long.
+pub fn fast_fill_slice(val: u32, dst: &mut [u32]) -> Result<(), CpuSetError> {
+    let bytes = (dst.len() * size_of::<u32>()) as u32;
+    let d = dst.as_mut_ptr() as u32;
+    let words = check_region(d, bytes, 4, 32)?;
+    unsafe { cpu_fast_set(&val as * const u32 as u32, d, words, SetMode::Fill) }
+    Ok(())
+}
+
+/// SoftReset (SWI 0x00): restarts the game from the start of ROM (or EWRAM for multiboot games).
+/// The IWRAM region used by the BIOS (0x03007E00 - 0x03007FFF) is cleared first.
+pub fn soft_reset() -> ! {
+    unsafe { asm!("swi 0x00" : : : "memory" : "volatile") }
+    loop {}
+}
+
+/// The memory regions and registers that `register_ram_reset` can clear.
+#[derive(Copy, Clone)]
+#[repr(u32)]
+pub enum ResetFlag {
+    /// 256KB of EWRAM. Never clear this from a multiboot game, which runs from EWRAM.
+    Ewram       = 0x01,
+    /// IWRAM, except for the last 0x200 bytes which are used by the BIOS (and hold the stacks).
+    Iwram       = 0x02,
+    Palette     = 0x04,
+    Vram        = 0x08,
+    Oam         = 0x10,
+    SioRegisters    = 0x20,
+    SoundRegisters  = 0x40,
+    /// All the other registers, except for the serial and sound ones.
+    OtherRegisters  = 0x80,
+}
+
+/// RegisterRamReset (SWI 0x01): clears the regions given by `flags`, a bitwise or of `ResetFlag`s.
+pub unsafe fn register_ram_reset(flags: u32) {
+    asm!("swi 0x01"
+    :
+    : "{r0}"(flags)
+    : "r0", "r1", "r2", "r3", "memory"
+    : "volatile");
+}
+
+/// Halt (SWI 0x02): puts the CPU to sleep until any enabled interrupt is requested.
+pub fn halt() {
+    unsafe { asm!("swi 0x02" : : : "r0", "r1", "r2", "r3", "memory" : "volatile") }
+}
+
+/// Stop (SWI 0x03): turns off most of the system until a keypad, serial or game pak interrupt
+/// wakes it up. The display and sound should be turned off before calling this.
+pub fn stop() {
+    unsafe { asm!("swi 0x03" : : : "r0", "r1", "r2", "r3", "memory" : "volatile") }
+}
+
+/// IntrWait (SWI 0x04): halts until one of the interrupts in `flags` (a bitwise or of `Irq`s) has
+/// been handled. If `discard_old` is set, interrupts that were handled before the call don't count.
+///
+/// This relies on the BIOS interrupt flags at 0x03007FF8, which the master ISR installed by
+/// `interrupt::init` keeps up to date.
+pub fn intr_wait(discard_old: bool, flags: u16) {
+    unsafe {
+        asm!("swi 0x04"
+        :
+        : "{r0}"(discard_old as u32), "{r1}"(flags as u32)
+        : "r0", "r1", "r2", "r3", "memory"
+        : "volatile");
+    }
+}
+
+/// Halts until the next `irq` interrupt has been handled; the same as
+/// `intr_wait(true, irq as u16)`.
+pub fn intr_wait_for(irq: Irq) {
+    intr_wait(true, irq as u16)
+}
+
+/// VBlankIntrWait (SWI 0x05): halts until the next VBlank interrupt has been handled. The VBlank
+/// interrupt has to be enabled (see `interrupt::enable`).
+pub fn vblank_intr_wait() {
+    unsafe { asm!("swi 0x05" : : : "r0", "r1", "r2", "r3", "memory" : "volatile") }
+}
+
+/// Div (SWI 0x06): returns the quotient and remainder of num / den, rounded towards zero.
+///
+/// The BIOS hangs when dividing by zero, so `den` must not be 0.
+pub fn div(num: i32, den: i32) -> (i32, i32) {
+    assert!(den != 0);
+    let (quot, rem): (i32, i32);
+    unsafe {
+        asm!("swi 0x06"
+        : "={r0}"(quot), "={r1}"(rem)
+        : "{r0}"(num), "{r1}"(den)
+        : "r3"
+        : "volatile");
+    }
+    (quot, rem)
+}
+
+/// DivArm (SWI 0x07): the same as `div`, with the arguments swapped in registers for
+/// compatibility with the ARM library. It is 3 cycles slower than `div`.
+pub fn div_arm(num: i32, den: i32) -> (i32, i32) {
+    assert!(den != 0);
+    let (quot, rem): (i32, i32);
+    unsafe {
+        asm!("swi 0x07"
+        : "={r0}"(quot), "={r1}"(rem)
+        : "{r0}"(den), "{r1}"(num)
+        : "r3"
+        : "volatile");
+    }
+    (quot, rem)
+}
+
+/// Sqrt (SWI 0x08): the integer square root of `n`, rounded down. To keep fractional precision,
+/// shift n left by 2N bits and the result will have N fractional bits.
+pub fn sqrt(n: u32) -> u16 {
+    let result: u32;
+    unsafe {
+        asm!("swi 0x08"
+        : "={r0}"(result)
+        : "{r0}"(n)
+        : "r1", "r3"
+        : "volatile");
+    }
+    result as u16
+}
+
+/// ArcTan (SWI 0x09): the arctangent of `tan` (a 1.14 fixed point number), as an angle in
+/// [-0x4000, 0x4000], where 0x10000 is a full circle.
+pub fn arctan(tan: i16) -> i16 {
+    let result: i32;
+    unsafe {
+        asm!("swi 0x09"
+        : "={r0}"(result)
+        : "{r0}"(tan as i32)
+        : "r1", "r3"
+        : "volatile");
+    }
+    result as i16
+}
+
+/// ArcTan2 (SWI 0x0A): the angle of the point (x, y) (both 1.14 fixed point numbers) in
+/// [0, 0xFFFF], where 0x10000 is a full circle.
+pub fn arctan2(x: i16, y: i16) -> u16 {
+    let result: u32;
+    unsafe {
+        asm!("swi 0x0A"
+        : "={r0}"(result)
+        : "{r0}"(x as i32), "{r1}"(y as i32)
+        : "r1", "r3"
+        : "volatile");
+    }
+    result as u16
+}
+
+/// GetBiosChecksum (SWI 0x0D): 0xBAAE187F on a GBA, and 0xBAAE1880 on a DS.
+pub fn bios_checksum() -> u32 {
+    let result: u32;
+    unsafe {
+        asm!("swi 0x0D"
+        : "={r0}"(result)
+        :
+        : "r1", "r3"
+        : "volatile");
+    }
+    result
+}
+
+/// The input of `bg_affine_set`: how a background is scrolled, scaled and rotated.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct BgAffineSource {
+    /// The point of the background (in 20.8 fixed point) that ends up at `screen_x, screen_y`.
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub screen_x: i16,
+    pub screen_y: i16,
+    /// 8.8 fixed point scale factors
+    pub scale_x: i16,
+    pub scale_y: i16,
+    /// Only the upper 8 bits are used; 0x10000 is a full circle
+    pub angle: u16,
+    _padding: u16,
+}
+
+impl BgAffineSource {
+    pub const fn new(origin_x: i32, origin_y: i32, screen_x: i16, screen_y: i16,
+                     scale_x: i16, scale_y: i16, angle: u16) -> Self {
+        BgAffineSource { origin_x, origin_y, screen_x, screen_y, scale_x, scale_y, angle, _padding: 0 }
+    }
+}
+
+/// The output of `bg_affine_set`, laid out like the BG2 / BG3 affine registers (BGxPA - BGxY).
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct BgAffineDest {
+    pub pa: i16,
+    pub pb: i16,
+    pub pc: i16,
+    pub pd: i16,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// BgAffineSet (SWI 0x0E): calculates the affine matrix and reference point for every source in
+/// `src`, writing the results to the same position in `dst`. Only `min(src.len(), dst.len())`
+/// entries are calculated.
+pub fn bg_affine_set(src: &[BgAffineSource], dst: &mut [BgAffineDest]) {
+    let count = if src.len() < dst.len() { src.len() } else { dst.len() };
+    unsafe {
+        asm!("swi 0x0E"
+        :
+        : "{r0}"(src.as_ptr()), "{r1}"(dst.as_mut_ptr()), "{r2}"(count as u32)
+        : "r0", "r1", "r2", "r3", "memory"
+        : "volatile");
+    }
+}
+
+/// The input of `obj_affine_set`: how a sprite is scaled and rotated.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ObjAffineSource {
+    /// 8.8 fixed point scale factors
+    pub scale_x: i16,
+    pub scale_y: i16,
+    /// Only the upper 8 bits are used; 0x10000 is a full circle
+    pub angle: u16,
+    _padding: u16,
+}
+
+impl ObjAffineSource {
+    pub const fn new(scale_x: i16, scale_y: i16, angle: u16) -> Self {
+        ObjAffineSource { scale_x, scale_y, angle, _padding: 0 }
+    }
+}
+
+/// ObjAffineSet (SWI 0x0F): calculates the affine matrix (pa, pb, pc, pd) for every source in
+/// `src`. The four values of each matrix are written `stride` bytes apart, starting at `dst`: use a
+/// stride of 2 to write them next to each other, or 8 to write them straight into OAM.
+pub unsafe fn obj_affine_set(src: &[ObjAffineSource], dst: * mut i16, stride: u32) {
+    asm!("swi 0x0F"
+    :
+    : "{r0}"(src.as_ptr()), "{r1}"(dst), "{r2}"(src.len() as u32), "{r3}"(stride)
+    : "r0", "r1", "r2", "r3", "memory"
+    : "volatile");
+}
+
+/// Describes the data unpacked by `bit_unpack`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct BitUnPackInfo {
+    /// The length of the source data, in bytes.
+    pub src_len: u16,
+    /// The width of a source unit in bits: 1, 2, 4 or 8.
+    pub src_width: u8,
+    /// The width of a destination unit in bits: 1, 2, 4, 8, 16 or 32.
+    pub dst_width: u8,
+    /// Added to every unit; if bit 31 is set, it is added to zero units too.
+    pub offset: u32,
+}
+
+/// BitUnPack (SWI 0x10): widens every unit of `src` to `info.dst_width` bits, adding the offset to
+/// it, e.g. to turn 1bpp font data into 4bpp tiles. Data is written to dst 32 bits at a time, so
+/// dst must be word aligned, and it is safe to use on VRAM.
+pub unsafe fn bit_unpack(src: * const u8, dst: * mut u32, info: &BitUnPackInfo) {
+    asm!("swi 0x10"
+    :
+    : "{r0}"(src), "{r1}"(dst), "{r2}"(info as * const BitUnPackInfo)
+    : "r0", "r1", "r2", "r3", "memory"
+    : "volatile");
+}
+
+/// SoundBias (SWI 0x19): slowly moves the sound bias to 0x200 if `enabled`, or to 0 otherwise,
+/// which avoids the click that writing SOUNDBIAS directly causes.
+pub fn sound_bias(enabled: bool) {
+    let level: u32 = if enabled { 0x200 } else { 0 };
+    unsafe {
+        asm!("swi 0x19"
+        :
+        : "{r0}"(level)
+        : "r0", "r1", "r2", "r3"
+        : "volatile");
+    }
+}
@@ -1,6 +1,8 @@
 use core::ops::{ Index, IndexMut };
-use core::mem::transmute;
+use core::mem::{ transmute, size_of };
 use ptr::Ptr;
+use mem;
+use bios::{ self, SetMode, UnitSize };
 
 pub struct StaticArr<T: Sized> where Ptr<T>: Clone + Copy {
     ptr: Ptr<T>,
@@ -17,8 +19,24 @@ impl<T: Sized + Clone + Copy> StaticArr<T> {
 
     pub fn len(&self) -> u32 { self.len }
 
-    pub fn zero(&self) -> u32 {
-        panic!()
+    /// Sets every byte of the array to zero, using the fastest BIOS routine that the alignment and
+    /// size of the array allow.
+    pub fn zero(&mut self) {
+        let zero = 0u32;
+        let src = &zero as * const u32 as u32;
+        let bytes = self.len * size_of::<T>() as u32;
+        unsafe {
+            let dst = self.ptr.num;
+            if dst & 3 == 0 && bytes & 31 == 0 {
+                bios::cpu_fast_set(src, dst, bytes >> 2, SetMode::Fill);
+            } else if dst & 3 == 0 && bytes & 3 == 0 {
+                bios::cpu_set(src, dst, bytes >> 2, SetMode::Fill, UnitSize::_32bit);
+            } else if dst & 1 == 0 && bytes & 1 == 0 {
+                bios::cpu_set(src, dst, bytes >> 1, SetMode::Fill, UnitSize::_16bit);
+            } else {
+                mem::memset(self.ptr, 0, self.len);
+            }
+        }
     }
 
     pub fn as_ptr(&self) -> Ptr<T> {
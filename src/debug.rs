@@ -0,0 +1,73 @@
+//! Logging to an emulator's debug console. Currently only mGBA's debug registers are supported;
+//! on hardware (or in emulators without them) nothing is written.
+use core::fmt;
+use core::intrinsics::{ volatile_load, volatile_store };
+
+const REG_DEBUG_ENABLE: u32 = 0x04FFF780;
+const REG_DEBUG_FLAGS: u32 = 0x04FFF700;
+const REG_DEBUG_STRING: u32 = 0x04FFF600;
+
+/// The number of bytes mGBA accepts per message.
+const MAX_MESSAGE_LEN: u32 = 0x100;
+
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum LogLevel {
+    Fatal   = 0,
+    Error   = 1,
+    Warn    = 2,
+    Info    = 3,
+    Debug   = 4,
+}
+
+/// A `fmt::Write` that sends every line written to it to mGBA's log. Lines longer than 256 bytes
+/// are split into several messages.
+pub struct MgbaLog {
+    level: LogLevel,
+    len: u32,
+}
+
+impl MgbaLog {
+    /// Enables mGBA's debug registers, returning `None` if they aren't available.
+    pub fn new(level: LogLevel) -> Option<MgbaLog> {
+        unsafe {
+            volatile_store(REG_DEBUG_ENABLE as * mut u16, 0xC0DE);
+            if volatile_load(REG_DEBUG_ENABLE as * const u16) == 0x1DEA {
+                Some(MgbaLog { level, len: 0 })
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Sends whatever has been written since the last message.
+    pub fn flush(&mut self) {
+        unsafe { volatile_store(REG_DEBUG_FLAGS as * mut u16, self.level as u16 | 0x100) }
+        self.len = 0;
+    }
+}
+
+impl fmt::Write for MgbaLog {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            if b == b'\n' {
+                self.flush();
+                continue;
+            }
+            if self.len == MAX_MESSAGE_LEN {
+                self.flush();
+            }
+            unsafe { volatile_store((REG_DEBUG_STRING + self.len) as * mut u8, b) }
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MgbaLog {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            self.flush();
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! The GBA has four DMA channels. Each one is represented by its own type so that ownership of a
+//! channel can be handed out through `Peripherals::take`, and so that channel specific
+//! functionality (only DMA3 can write to game pak memory, only DMA1 and DMA2 can feed the sound
+//! FIFOs) can be expressed in the type system.
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/dma.htm
+use core::mem::size_of;
+use core::intrinsics::{ volatile_load, volatile_store };
+use reg;
+
+/// Exclusive handle to DMA channel 0, which has the highest priority of the four channels.
+pub struct Dma0 { _private: () }
+
+/// Exclusive handle to DMA channel 1.
+pub struct Dma1 { _private: () }
+
+/// Exclusive handle to DMA channel 2.
+pub struct Dma2 { _private: () }
+
+/// Exclusive handle to DMA channel 3, which has the lowest priority of the four channels.
+pub struct Dma3 { _private: () }
+
+impl Dma0 { pub(crate) const unsafe fn new() -> Self { Dma0 { _private: () } } }
+impl Dma1 { pub(crate) const unsafe fn new() -> Self { Dma1 { _private: () } } }
+impl Dma2 { pub(crate) const unsafe fn new() -> Self { Dma2 { _private: () } } }
+impl Dma3 { pub(crate) const unsafe fn new() -> Self { Dma3 { _private: () } } }
+
+/// How the destination address changes after every unit is transferred.
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum DestAddrControl {
+    Increment       = 0x0000_u16,
+    Decrement       = 0x0020_u16,
+    Fixed           = 0x0040_u16,
+    /// Increments during the transfer, and is reset to the initial address whenever a repeating
+    /// transfer starts again.
+    IncrementReload = 0x0060_u16,
+}
+
+/// How the source address changes after every unit is transferred.
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum SrcAddrControl {
+    Increment       = 0x0000_u16,
+    Decrement       = 0x0080_u16,
+    Fixed           = 0x0100_u16,
+}
+
+/// The size of a single unit transferred by DMA.
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum DmaUnit {
+    _16bit          = 0x0000_u16,
+    _32bit          = 0x0400_u16,
+}
+
+impl DmaUnit {
+    pub fn bytes(self) -> u32 {
+        match self {
+            DmaUnit::_16bit => 2,
+            DmaUnit::_32bit => 4,
+        }
+    }
+}
+
+/// When a transfer starts.
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum StartTiming {
+    Immediate       = 0x0000_u16,
+    VBlank          = 0x1000_u16,
+    HBlank          = 0x2000_u16,
+    /// Sound FIFO requests for DMA1 and DMA2, video capture for DMA3. Not allowed for DMA0.
+    Special         = 0x3000_u16,
+}
+
+/// Represents a DMA control register (DMAxCNT_H) as per https://www.coranac.com/tonc/text/dma.htm
+#[derive(Copy, Clone)]
+pub struct DmaControl(u16);
+
+impl DmaControl {
+    const DEST_MASK: u16 =      0x0060;
+    const SRC_MASK: u16 =       0x0180;
+    const REPEAT_MASK: u16 =    0x0200;
+    const UNIT_MASK: u16 =      0x0400;
+    const TIMING_MASK: u16 =    0x3000;
+    const IRQ_MASK: u16 =       0x4000;
+    const ENABLE_MASK: u16 =    0x8000;
+
+    /// An immediate, non repeating, incrementing 16-bit transfer.
+    pub const fn new() -> Self { DmaControl(0) }
+
+    pub fn set_dest_control(&mut self, dest: DestAddrControl) -> &mut Self {
+        self.0 &= !Self::DEST_MASK;
+        self.0 |= dest as u16;
+        self
+    }
+
+    pub fn set_src_control(&mut self, src: SrcAddrControl) -> &mut Self {
+        self.0 &= !Self::SRC_MASK;
+        self.0 |= src as u16;
+        self
+    }
+
+    /// If enabled, the transfer starts again every time the start timing is triggered, until the
+    /// channel is stopped.
+    pub fn set_repeat(&mut self, repeat: bool) -> &mut Self {
+        self.0 &= !Self::REPEAT_MASK;
+        self.0 |= (repeat as u16) << 9;
+        self
+    }
+
+    pub fn set_unit(&mut self, unit: DmaUnit) -> &mut Self {
+        self.0 &= !Self::UNIT_MASK;
+        self.0 |= unit as u16;
+        self
+    }
+
+    pub fn set_start_timing(&mut self, timing: StartTiming) -> &mut Self {
+        self.0 &= !Self::TIMING_MASK;
+        self.0 |= timing as u16;
+        self
+    }
+
+    /// If enabled, the channel raises its DMA interrupt when a transfer completes.
+    pub fn set_irq_enabled(&mut self, irq: bool) -> &mut Self {
+        self.0 &= !Self::IRQ_MASK;
+        self.0 |= (irq as u16) << 14;
+        self
+    }
+
+    pub fn start_timing(&self) -> StartTiming {
+        match self.0 & Self::TIMING_MASK {
+            0x0000 => StartTiming::Immediate,
+            0x1000 => StartTiming::VBlank,
+            0x2000 => StartTiming::HBlank,
+            _ => StartTiming::Special,
+        }
+    }
+
+    pub fn unit(&self) -> DmaUnit {
+        if self.0 & Self::UNIT_MASK != 0 { DmaUnit::_32bit } else { DmaUnit::_16bit }
+    }
+
+    pub fn bits(&self) -> u16 { self.0 }
+}
+
+/// The reasons a slice based DMA transfer can be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DmaError {
+    /// The source and destination slices have different lengths.
+    LengthMismatch,
+    /// The source or destination isn't aligned to the unit size.
+    Misaligned,
+    /// The length in bytes isn't a multiple of the unit size.
+    BadLength,
+    /// The transfer has more units than the channel's count register allows.
+    TooLong,
+    /// The start timing isn't available on this channel (`StartTiming::Special` on DMA0).
+    BadTiming,
+    /// The source is in game pak memory (0x08000000 - 0x0DFFFFFF), which DMA0 can't read.
+    GamePakSource,
+}
+
+mod sealed {
+    pub trait Channel {}
+    impl Channel for super::Dma0 {}
+    impl Channel for super::Dma1 {}
+    impl Channel for super::Dma2 {}
+    impl Channel for super::Dma3 {}
+
+    pub trait SoundChannel {}
+    impl SoundChannel for super::Dma1 {}
+    impl SoundChannel for super::Dma2 {}
+
+    pub trait Unit {}
+    impl Unit for u16 {}
+    impl Unit for u32 {}
+}
+
+/// The element types `DmaChannel::fill` can fill: plain integers of a DMA unit's width, for which
+/// every bit pattern is a valid value.
+pub trait FillUnit: Copy + sealed::Unit {
+    const UNIT: DmaUnit;
+}
+
+impl FillUnit for u16 { const UNIT: DmaUnit = DmaUnit::_16bit; }
+impl FillUnit for u32 { const UNIT: DmaUnit = DmaUnit::_32bit; }
+
+/// Functionality shared by all four DMA channels. Only implemented by the channel handles.
+pub trait DmaChannel: sealed::Channel {
+    /// Which channel this is, [0, 3].
+    const INDEX: u32;
+
+    /// The largest number of units a single transfer can move: 0x4000 for DMA0-2, and 0x10000 for
+    /// DMA3.
+    const MAX_UNITS: u32;
+
+    #[inline(always)]
+    fn base() -> u32 { unsafe { reg::REG_DMA_BASE.num } + Self::INDEX * 12 }
+
+    /// Starts a transfer of `units` units from src to dst. Both addresses must be aligned to the
+    /// unit size in `control`. Immediate transfers halt the CPU and are finished by the time this
+    /// returns. DMA0 rejects the special start timing and game pak sources.
+    unsafe fn start(&mut self, src: u32, dst: u32, units: u32, control: DmaControl) -> Result<(), DmaError> {
+        if Self::INDEX == 0 {
+            if let StartTiming::Special = control.start_timing() {
+                return Err(DmaError::BadTiming);
+            }
+            if src >= 0x08000000 && src < 0x0E000000 {
+                return Err(DmaError::GamePakSource);
+            }
+        }
+        let base = Self::base();
+        // A channel has to be disabled before it can be reconfigured
+        volatile_store((base + 10) as * mut u16, 0);
+        volatile_store(base as * mut u32, src);
+        volatile_store((base + 4) as * mut u32, dst);
+        // A count of 0 means the maximum number of units
+        volatile_store((base + 8) as * mut u16, (units & (Self::MAX_UNITS - 1)) as u16);
+        volatile_store((base + 10) as * mut u16, control.0 | DmaControl::ENABLE_MASK);
+        Ok(())
+    }
+
+    /// Stops the channel, cancelling any repeating transfer.
+    fn stop(&mut self) {
+        unsafe { volatile_store((Self::base() + 10) as * mut u16, 0) }
+    }
+
+    /// The enable bit stays set while a transfer is pending or running, and for as long as a
+    /// repeating transfer is active.
+    fn is_enabled(&self) -> bool {
+        unsafe { volatile_load((Self::base() + 10) as * const u16) & DmaControl::ENABLE_MASK != 0 }
+    }
+
+    /// Copies src into dst immediately, using 32-bit units if both slices allow it.
+    fn copy<T: Copy>(&mut self, src: &[T], dst: &mut [T]) -> Result<(), DmaError> {
+        if src.len() != dst.len() {
+            return Err(DmaError::LengthMismatch);
+        }
+        let bytes = (src.len() * size_of::<T>()) as u32;
+        let (s, d) = (src.as_ptr() as u32, dst.as_mut_ptr() as u32);
+        let unit = if (s | d | bytes) & 3 == 0 { DmaUnit::_32bit } else { DmaUnit::_16bit };
+        let units = check_region::<Self>(s | d, bytes, unit)?;
+        if units != 0 {
+            unsafe { self.start(s, d, units, *DmaControl::new().set_unit(unit))? }
+        }
+        Ok(())
+    }
+
+    /// Fills dst with `val` immediately, in units of the element size.
+    fn fill<T: FillUnit>(&mut self, val: T, dst: &mut [T]) -> Result<(), DmaError> {
+        let unit = T::UNIT;
+        let bytes = (dst.len() * size_of::<T>()) as u32;
+        let d = dst.as_mut_ptr() as u32;
+        let units = check_region::<Self>(d, bytes, unit)?;
+        // The source is read for every unit, so it has to stay put in memory
+        let src = &val as * const T as u32;
+        if units != 0 {
+            let mut control = DmaControl::new();
+            control.set_unit(unit).set_src_control(SrcAddrControl::Fixed);
+            unsafe { self.start(src, d, units, control)? }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `addr` and `bytes` describe whole, aligned units that fit in one transfer of `C`,
+/// returning the unit count.
+fn check_region<C: DmaChannel + ?Sized>(addr: u32, bytes: u32, unit: DmaUnit) -> Result<u32, DmaError> {
+    let size = unit.bytes();
+    if addr & (size - 1) != 0 {
+        Err(DmaError::Misaligned)
+    } else if bytes & (size - 1) != 0 {
+        Err(DmaError::BadLength)
+    } else if bytes / size > C::MAX_UNITS {
+        Err(DmaError::TooLong)
+    } else {
+        Ok(bytes / size)
+    }
+}
+
+impl DmaChannel for Dma0 { const INDEX: u32 = 0; const MAX_UNITS: u32 = 0x4000; }
+impl DmaChannel for Dma1 { const INDEX: u32 = 1; const MAX_UNITS: u32 = 0x4000; }
+impl DmaChannel for Dma2 { const INDEX: u32 = 2; const MAX_UNITS: u32 = 0x4000; }
+impl DmaChannel for Dma3 { const INDEX: u32 = 3; const MAX_UNITS: u32 = 0x10000; }
+
+/// The two Direct Sound FIFOs.
+#[derive(Copy, Clone)]
+pub enum Fifo {
+    A,
+    B,
+}
+
+impl Fifo {
+    pub fn address(self) -> u32 {
+        match self {
+            Fifo::A => unsafe { reg::REG_FIFO_A.num },
+            Fifo::B => unsafe { reg::REG_FIFO_B.num },
+        }
+    }
+}
+
+/// The channels that can feed a Direct Sound FIFO: DMA1 and DMA2. Only implemented by their
+/// handles.
+pub trait SoundDma: DmaChannel + sealed::SoundChannel {
+    /// Starts feeding `samples` into `fifo`. Whenever the FIFO runs low it requests another 4 words,
+    /// until the channel is stopped; the caller has to stop (or restart) the channel before it
+    /// reads past the end of `samples`.
+    fn start_fifo(&mut self, samples: &'static [u32], fifo: Fifo) {
+        let mut control = DmaControl::new();
+        control.set_dest_control(DestAddrControl::Fixed)
+            .set_repeat(true)
+            .set_unit(DmaUnit::_32bit)
+            .set_start_timing(StartTiming::Special);
+        // The count is ignored in FIFO mode; the hardware always transfers 4 words. DMA1 and DMA2
+        // accept the special start timing, so this can't fail.
+        let _ = unsafe { self.start(samples.as_ptr() as u32, fifo.address(), 4, control) };
+    }
+}
+
+impl SoundDma for Dma1 {}
+impl SoundDma for Dma2 {}
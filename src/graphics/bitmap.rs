@@ -0,0 +1,424 @@
+//! Framebuffers for the bitmap video modes, with basic drawing primitives:
+//!
+//! * Mode 3: a single 240x160 page of 16-bit colors.
+//! * Mode 4: two 240x160 pages of 8-bit indices into the background palette.
+//! * Mode 5: two 160x128 pages of 16-bit colors.
+//!
+//! All of them are displayed through background 2. Coordinates outside of the framebuffer are
+//! clipped, so shapes may be partially (or entirely) off screen.
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/bitmaps.htm
+use core::intrinsics::{ volatile_load, volatile_store };
+use graphics::{ GraphicsMode, VideoMode, FrameBufferStart, ColorMode };
+use bios::{ self, SetMode };
+
+/// An image from `img_as_palleted_sprite_4bpp!` or `img_as_palleted_sprite_8bpp!`: the palette,
+/// and the palette indices of the pixels laid out in 8x8 tiles.
+#[derive(Copy, Clone)]
+pub struct Image {
+    pub palette: &'static [u16],
+    pub pixels: &'static [u8],
+    /// 4bpp pixels are packed two to a byte, low nibble first.
+    pub color_mode: ColorMode,
+}
+
+impl Image {
+    /// Wraps the output of `img_as_palleted_sprite_4bpp!`.
+    pub fn from_4bpp((palette, pixels): (&'static [u16], &'static [u8])) -> Image {
+        Image { palette, pixels, color_mode: ColorMode::_4bpp }
+    }
+
+    /// Wraps the output of `img_as_palleted_sprite_8bpp!`.
+    pub fn from_8bpp((palette, pixels): (&'static [u16], &'static [u8])) -> Image {
+        Image { palette, pixels, color_mode: ColorMode::_8bpp }
+    }
+
+    /// The palette index of pixel (x, y) of an image that is `width` pixels wide.
+    fn index(&self, x: i32, y: i32, width: i32) -> u8 {
+        let tile = (y / 8) * (width / 8) + x / 8;
+        let n = (tile * 64 + (y % 8) * 8 + x % 8) as usize;
+        match self.color_mode {
+            ColorMode::_4bpp => (self.pixels[n / 2] >> ((n & 1) * 4)) & 0x0F,
+            ColorMode::_8bpp => self.pixels[n],
+        }
+    }
+
+    /// The number of pixels in the image.
+    fn pixel_count(&self) -> usize {
+        match self.color_mode {
+            ColorMode::_4bpp => self.pixels.len() * 2,
+            ColorMode::_8bpp => self.pixels.len(),
+        }
+    }
+}
+
+/// The address of a page of a bitmap mode.
+fn page_address(page: FrameBufferStart) -> u32 {
+    match page {
+        FrameBufferStart::Base => 0x06000000,
+        FrameBufferStart::Offset => 0x0600A000,
+    }
+}
+
+/// Switches to `vm` with only background 2 enabled, displaying `page`.
+fn enable_mode(vm: VideoMode, page: FrameBufferStart) {
+    GraphicsMode::modify(|m| {
+        m.vm = vm;
+        m.frame_buffer_start = page;
+        m.bg0_enabled = false;
+        m.bg1_enabled = false;
+        m.bg2_enabled = true;
+        m.bg3_enabled = false;
+    });
+}
+
+fn other_page(page: FrameBufferStart) -> FrameBufferStart {
+    match page {
+        FrameBufferStart::Base => FrameBufferStart::Offset,
+        FrameBufferStart::Offset => FrameBufferStart::Base,
+    }
+}
+
+/// Drawing primitives shared by every bitmap framebuffer. Only `write` and `read` are specific to a
+/// mode; they take coordinates that have already been clipped.
+pub trait Surface {
+    /// A 16-bit color, or a palette index in mode 4.
+    type Pixel: Copy;
+
+    const WIDTH: i32;
+    const HEIGHT: i32;
+
+    /// Writes a pixel. x and y must be inside the framebuffer.
+    unsafe fn write(&mut self, x: i32, y: i32, color: Self::Pixel);
+
+    /// Reads a pixel. x and y must be inside the framebuffer.
+    unsafe fn read(&self, x: i32, y: i32) -> Self::Pixel;
+
+    /// The pixel that draws entry `index` of `palette`.
+    fn from_palette(palette: &[u16], index: u8) -> Self::Pixel;
+
+    /// Fills the whole framebuffer with `color`.
+    fn clear(&mut self, color: Self::Pixel);
+
+    fn contains(x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < Self::WIDTH && y < Self::HEIGHT
+    }
+
+    fn plot(&mut self, x: i32, y: i32, color: Self::Pixel) {
+        if Self::contains(x, y) {
+            unsafe { self.write(x, y, color) }
+        }
+    }
+
+    /// The pixel at (x, y), or `None` if it is outside the framebuffer.
+    fn get(&self, x: i32, y: i32) -> Option<Self::Pixel> {
+        if Self::contains(x, y) { Some(unsafe { self.read(x, y) }) } else { None }
+    }
+
+    /// A horizontal line from x0 to x1, both included.
+    fn hline(&mut self, x0: i32, x1: i32, y: i32, color: Self::Pixel) {
+        if y < 0 || y >= Self::HEIGHT {
+            return;
+        }
+        let (x0, x1) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
+        for x in x0.max(0)..x1.min(Self::WIDTH - 1) + 1 {
+            unsafe { self.write(x, y, color) }
+        }
+    }
+
+    /// A vertical line from y0 to y1, both included.
+    fn vline(&mut self, x: i32, y0: i32, y1: i32, color: Self::Pixel) {
+        if x < 0 || x >= Self::WIDTH {
+            return;
+        }
+        let (y0, y1) = if y0 <= y1 { (y0, y1) } else { (y1, y0) };
+        for y in y0.max(0)..y1.min(Self::HEIGHT - 1) + 1 {
+            unsafe { self.write(x, y, color) }
+        }
+    }
+
+    /// A line from (x0, y0) to (x1, y1), both included, using Bresenham's algorithm.
+    fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Self::Pixel) {
+        if y0 == y1 {
+            return self.hline(x0, x1, y0, color);
+        }
+        if x0 == x1 {
+            return self.vline(x0, y0, y1, color);
+        }
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// The outline of a w by h rectangle with its top left corner at (x, y).
+    fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: Self::Pixel) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let (right, bottom) = (x.saturating_add(w - 1), y.saturating_add(h - 1));
+        self.hline(x, right, y, color);
+        self.hline(x, right, bottom, color);
+        self.vline(x, y, bottom, color);
+        self.vline(right, y, bottom, color);
+    }
+
+    /// A filled w by h rectangle with its top left corner at (x, y).
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: Self::Pixel) {
+        if w <= 0 || h <= 0 {
+            return;
+        }
+        let right = x.saturating_add(w - 1);
+        for row in y.max(0)..y.saturating_add(h).min(Self::HEIGHT) {
+            self.hline(x, right, row, color);
+        }
+    }
+
+    /// The outline of a circle, using the midpoint algorithm.
+    fn circle(&mut self, cx: i32, cy: i32, r: i32, color: Self::Pixel) {
+        if r < 0 {
+            return;
+        }
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            self.plot(cx + x, cy + y, color);
+            self.plot(cx - x, cy + y, color);
+            self.plot(cx + x, cy - y, color);
+            self.plot(cx - x, cy - y, color);
+            self.plot(cx + y, cy + x, color);
+            self.plot(cx - y, cy + x, color);
+            self.plot(cx + y, cy - x, color);
+            self.plot(cx - y, cy - x, color);
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// A filled circle.
+    fn fill_circle(&mut self, cx: i32, cy: i32, r: i32, color: Self::Pixel) {
+        if r < 0 {
+            return;
+        }
+        let (mut x, mut y, mut err) = (r, 0, 1 - r);
+        while x >= y {
+            self.hline(cx - x, cx + x, cy + y, color);
+            self.hline(cx - x, cx + x, cy - y, color);
+            self.hline(cx - y, cx + y, cy + x, color);
+            self.hline(cx - y, cx + y, cy - x, color);
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Draws `image` with its top left corner at (x, y). `width` is the width of the image in
+    /// pixels, a multiple of 8. Palette index 0 is transparent, like it is for sprites. In mode 4
+    /// the pixels are palette indices, so the image's palette has to be loaded into the background
+    /// palette (see `Palettes::bg`).
+    fn blit(&mut self, x: i32, y: i32, width: u32, image: Image) {
+        if width < 8 || width % 8 != 0 || width > i32::max_value() as u32 {
+            return;
+        }
+        let width = width as i32;
+        let height = (image.pixel_count() / width as usize) as i32;
+        for py in 0..height {
+            for px in 0..width {
+                let index = image.index(px, py, width);
+                if index != 0 {
+                    self.plot(x.saturating_add(px), y.saturating_add(py), Self::from_palette(image.palette, index));
+                }
+            }
+        }
+    }
+}
+
+/// The mode 3 framebuffer: 240x160 pixels, 16-bit colors, a single page.
+pub struct Mode3 { _private: () }
+
+impl Mode3 {
+    /// Switches the display to mode 3.
+    ///
+    /// Unsafe because the framebuffer doesn't own VRAM: only one framebuffer may exist at a time,
+    /// and nothing else may use background 2 or write to the framebuffer's part of VRAM while it
+    /// does.
+    pub unsafe fn enable() -> Self {
+        enable_mode(VideoMode::Mode3, FrameBufferStart::Base);
+        Mode3 { _private: () }
+    }
+}
+
+impl Surface for Mode3 {
+    type Pixel = u16;
+
+    const WIDTH: i32 = 240;
+    const HEIGHT: i32 = 160;
+
+    unsafe fn write(&mut self, x: i32, y: i32, color: u16) {
+        volatile_store((0x06000000 + ((y * Self::WIDTH + x) * 2) as u32) as * mut u16, color)
+    }
+
+    unsafe fn read(&self, x: i32, y: i32) -> u16 {
+        volatile_load((0x06000000 + ((y * Self::WIDTH + x) * 2) as u32) as * const u16)
+    }
+
+    fn from_palette(palette: &[u16], index: u8) -> u16 {
+        palette.get(index as usize).cloned().unwrap_or(0)
+    }
+
+    fn clear(&mut self, color: u16) {
+        let val = color as u32 | ((color as u32) << 16);
+        unsafe { bios::cpu_fast_set(&val as * const u32 as u32, 0x06000000, 240 * 160 / 2, SetMode::Fill) }
+    }
+}
+
+/// The mode 4 framebuffer: two pages of 240x160 pixels, 8-bit indices into the background palette.
+/// Drawing goes to the page that isn't displayed, and `flip` swaps the two.
+///
+/// VRAM ignores 8-bit writes, so every pixel is written by reading the halfword it shares with its
+/// neighbour and writing it back.
+pub struct Mode4 { draw_page: FrameBufferStart }
+
+impl Mode4 {
+    /// Switches the display to mode 4, displaying the first page and drawing to the second.
+    ///
+    /// Unsafe because the framebuffer doesn't own VRAM: only one framebuffer may exist at a time,
+    /// and nothing else may use background 2 or write to the framebuffer's part of VRAM while it
+    /// does.
+    pub unsafe fn enable() -> Self {
+        enable_mode(VideoMode::Mode4, FrameBufferStart::Base);
+        Mode4 { draw_page: FrameBufferStart::Offset }
+    }
+
+    /// The page that is drawn to.
+    pub fn draw_page(&self) -> FrameBufferStart {
+        self.draw_page
+    }
+
+    /// Displays the page that has been drawn to, and starts drawing to the other one. Should be
+    /// called during VBlank to avoid tearing.
+    pub fn flip(&mut self) {
+        let shown = self.draw_page;
+        GraphicsMode::modify(|m| m.frame_buffer_start = shown);
+        self.draw_page = other_page(shown);
+    }
+
+    fn address(&self, x: i32, y: i32) -> u32 {
+        page_address(self.draw_page) + (y * Self::WIDTH + x) as u32
+    }
+}
+
+impl Surface for Mode4 {
+    type Pixel = u8;
+
+    const WIDTH: i32 = 240;
+    const HEIGHT: i32 = 160;
+
+    unsafe fn write(&mut self, x: i32, y: i32, index: u8) {
+        let addr = self.address(x, y);
+        let p = (addr & !1) as * mut u16;
+        let old = volatile_load(p);
+        let new = if addr & 1 == 0 {
+            (old & 0xFF00) | index as u16
+        } else {
+            (old & 0x00FF) | ((index as u16) << 8)
+        };
+        volatile_store(p, new)
+    }
+
+    unsafe fn read(&self, x: i32, y: i32) -> u8 {
+        let addr = self.address(x, y);
+        let half = volatile_load((addr & !1) as * const u16);
+        (half >> ((addr & 1) * 8)) as u8
+    }
+
+    fn from_palette(_palette: &[u16], index: u8) -> u8 {
+        index
+    }
+
+    fn clear(&mut self, index: u8) {
+        let val = index as u32 * 0x01010101;
+        let dst = page_address(self.draw_page);
+        unsafe { bios::cpu_fast_set(&val as * const u32 as u32, dst, 240 * 160 / 4, SetMode::Fill) }
+    }
+}
+
+/// The mode 5 framebuffer: two pages of 160x128 pixels, 16-bit colors. Drawing goes to the page
+/// that isn't displayed, and `flip` swaps the two.
+pub struct Mode5 { draw_page: FrameBufferStart }
+
+impl Mode5 {
+    /// Switches the display to mode 5, displaying the first page and drawing to the second.
+    ///
+    /// Unsafe because the framebuffer doesn't own VRAM: only one framebuffer may exist at a time,
+    /// and nothing else may use background 2 or write to the framebuffer's part of VRAM while it
+    /// does.
+    pub unsafe fn enable() -> Self {
+        enable_mode(VideoMode::Mode5, FrameBufferStart::Base);
+        Mode5 { draw_page: FrameBufferStart::Offset }
+    }
+
+    /// The page that is drawn to.
+    pub fn draw_page(&self) -> FrameBufferStart {
+        self.draw_page
+    }
+
+    /// Displays the page that has been drawn to, and starts drawing to the other one. Should be
+    /// called during VBlank to avoid tearing.
+    pub fn flip(&mut self) {
+        let shown = self.draw_page;
+        GraphicsMode::modify(|m| m.frame_buffer_start = shown);
+        self.draw_page = other_page(shown);
+    }
+
+    fn address(&self, x: i32, y: i32) -> u32 {
+        page_address(self.draw_page) + ((y * Self::WIDTH + x) * 2) as u32
+    }
+}
+
+impl Surface for Mode5 {
+    type Pixel = u16;
+
+    const WIDTH: i32 = 160;
+    const HEIGHT: i32 = 128;
+
+    unsafe fn write(&mut self, x: i32, y: i32, color: u16) {
+        volatile_store(self.address(x, y) as * mut u16, color)
+    }
+
+    unsafe fn read(&self, x: i32, y: i32) -> u16 {
+        volatile_load(self.address(x, y) as * const u16)
+    }
+
+    fn from_palette(palette: &[u16], index: u8) -> u16 {
+        palette.get(index as usize).cloned().unwrap_or(0)
+    }
+
+    fn clear(&mut self, color: u16) {
+        let val = color as u32 | ((color as u32) << 16);
+        let dst = page_address(self.draw_page);
+        unsafe { bios::cpu_fast_set(&val as * const u32 as u32, dst, 160 * 128 / 2, SetMode::Fill) }
+    }
+}
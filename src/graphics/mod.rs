@@ -1,11 +1,16 @@
-use core::mem;
 use core::default::Default;
 use core::intrinsics::volatile_store;
 use reg;
+use bios;
 
 pub mod tiled_bg;
 pub mod sprites;
+pub mod palette;
+pub mod scanline;
+pub mod raster;
+pub mod bitmap;
 pub use self::sprites::*;
+pub use self::palette::Palettes;
 
 pub fn vsync_busy() {
     unsafe {
@@ -15,8 +20,10 @@ pub fn vsync_busy() {
     }
 }
 
+/// Waits for the next VBlank using the BIOS, which keeps the CPU halted in the meantime. The
+/// VBlank interrupt has to be enabled (see `interrupt::enable`).
 pub fn vsync_int() {
-    interrupt!(0x05);
+    bios::vblank_intr_wait();
 }
 
 #[derive(Copy, Clone)]
@@ -26,7 +33,7 @@ pub enum ColorMode {
     _8bpp   = 0x2000_u16,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum VideoMode {
     Mode0 = 0,
@@ -35,18 +42,35 @@ pub enum VideoMode {
     Mode3 = 3,
     Mode4 = 4,
     Mode5 = 5,
+    /// Not a valid mode; only here so that every DISPCNT value can be represented.
+    Prohibited6 = 6,
+    /// Not a valid mode; only here so that every DISPCNT value can be represented.
+    Prohibited7 = 7,
 }
 
 impl VideoMode {
     const MASK: u8 = 0b0000_0111_u8;
 
+    pub fn from_bits(val: u16) -> VideoMode {
+        match val as u8 & Self::MASK {
+            0 => VideoMode::Mode0,
+            1 => VideoMode::Mode1,
+            2 => VideoMode::Mode2,
+            3 => VideoMode::Mode3,
+            4 => VideoMode::Mode4,
+            5 => VideoMode::Mode5,
+            6 => VideoMode::Prohibited6,
+            _ => VideoMode::Prohibited7,
+        }
+    }
+
     pub fn set(self, val: u32) -> u32 {
         let p = self as u32;
         (val & !(Self::MASK as u32)) | p
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum FrameBufferStart {
     /// The FrameBuffer should start at the address 0x06000000
@@ -58,13 +82,17 @@ pub enum FrameBufferStart {
 impl FrameBufferStart {
     const MASK: u8 = 0b0001_0000_u8;
 
+    pub fn from_bits(val: u16) -> FrameBufferStart {
+        if val as u8 & Self::MASK != 0 { FrameBufferStart::Offset } else { FrameBufferStart::Base }
+    }
+
     pub fn set(self, val: u32) -> u32 {
         let p = self as u32;
         (val & !(Self::MASK as u32)) | p
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum SpriteStorageMode {
     _2D = 0b0000_0000,
@@ -74,13 +102,17 @@ pub enum SpriteStorageMode {
 impl SpriteStorageMode {
     const MASK: u8 = 0b0100_0000_u8;
 
+    pub fn from_bits(val: u16) -> SpriteStorageMode {
+        if val as u8 & Self::MASK != 0 { SpriteStorageMode::_1D } else { SpriteStorageMode::_2D }
+    }
+
     pub fn set(self, val: u32) -> u32 {
         let p = self as u32;
         (val & !(Self::MASK as u32)) | p
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum HBlankProcessing {
     None  = 0b0000_0000_u8,
@@ -90,13 +122,17 @@ pub enum HBlankProcessing {
 impl HBlankProcessing {
     const MASK: u8 = 0b0010_0000_u8;
 
+    pub fn from_bits(val: u16) -> HBlankProcessing {
+        if val as u8 & Self::MASK != 0 { HBlankProcessing::Force } else { HBlankProcessing::None }
+    }
+
     pub fn set(self, val: u32) -> u32 {
         let p = self as u32;
         (val & !(Self::MASK as u32)) | p
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum DisplayState {
     Blank   = 0b1000_0000_u8,
@@ -106,14 +142,23 @@ pub enum DisplayState {
 impl DisplayState {
     const MASK: u8 = 0b1000_0000_u8;
 
+    pub fn from_bits(val: u16) -> DisplayState {
+        if val as u8 & Self::MASK != 0 { DisplayState::Blank } else { DisplayState::On }
+    }
+
     pub fn set(self, val: u32) -> u32 {
         let p = self as u32;
         (val & !(Self::MASK as u32)) | p
     }
 }
 
+/// Represents the display control register (DISPCNT). Every bit of the register has a field, so
+/// `GraphicsMode::from_u16(n).to_u16() == n` for any n.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct GraphicsMode {
     pub vm: VideoMode,
+    /// Set by the BIOS when a GBC cartridge is running; read only for GBA software.
+    pub cgb_mode: bool,
     pub frame_buffer_start: FrameBufferStart,
     pub hblank_policy: HBlankProcessing,
     pub sprite_storage_mode: SpriteStorageMode,
@@ -130,27 +175,28 @@ pub struct GraphicsMode {
 
 impl GraphicsMode {
 
+    const CGB_MASK: u16 = 0x0008;
     const BG0_MASK: u16 = 0x0100;
     const BG1_MASK: u16 = 0x0200;
     const BG2_MASK: u16 = 0x0400;
     const BG3_MASK: u16 = 0x0800;
     const SPRITES_MASK: u16 = 0x1000;
     const WINDOW0_MASK: u16 = 0x2000;
-    const WINDOW1_MASK: u16 = 0x2000;
-    const SPRITE_WINDOWS_MASK: u16 = 0x2000;
-
+    const WINDOW1_MASK: u16 = 0x4000;
+    const SPRITE_WINDOWS_MASK: u16 = 0x8000;
 
     pub fn current() -> GraphicsMode {
-        unsafe { GraphicsMode::from_u16(mem::transmute(*reg::REG_GRAPHICS_MODE)) }
+        unsafe { GraphicsMode::from_u16(reg::REG_GRAPHICS_MODE.volatile_load()) }
     }
 
     pub fn from_u16(n: u16) -> GraphicsMode {
         GraphicsMode {
-            vm: VideoMode::Mode0,
-            frame_buffer_start:     FrameBufferStart::Base,
-            hblank_policy:          HBlankProcessing::None,
-            sprite_storage_mode:    SpriteStorageMode::_2D,
-            display_state:          DisplayState::On,
+            vm:                     VideoMode::from_bits(n),
+            cgb_mode:               (n & GraphicsMode::CGB_MASK) != 0,
+            frame_buffer_start:     FrameBufferStart::from_bits(n),
+            hblank_policy:          HBlankProcessing::from_bits(n),
+            sprite_storage_mode:    SpriteStorageMode::from_bits(n),
+            display_state:          DisplayState::from_bits(n),
             bg0_enabled:            (n & GraphicsMode::BG0_MASK) != 0,
             bg1_enabled:            (n & GraphicsMode::BG1_MASK) != 0,
             bg2_enabled:            (n & GraphicsMode::BG2_MASK) != 0,
@@ -162,26 +208,29 @@ impl GraphicsMode {
         }
     }
 
-    pub fn set(&self) {
+    pub fn to_u16(&self) -> u16 {
         let mut reg = 0u16;
         reg |= self.vm as u16;
         reg |= self.frame_buffer_start as u16;
         reg |= self.hblank_policy as u16;
-        reg |= self.sprite_windows_enabled as u16;
         reg |= self.display_state as u16;
         reg |= self.sprite_storage_mode as u16;
 
+        let cgb = if self.cgb_mode { GraphicsMode::CGB_MASK } else { 0 };
         let bg0 = if self.bg0_enabled { GraphicsMode::BG0_MASK } else { 0 };
         let bg1 = if self.bg1_enabled { GraphicsMode::BG1_MASK } else { 0 };
         let bg2 = if self.bg2_enabled { GraphicsMode::BG2_MASK } else { 0 };
         let bg3 = if self.bg3_enabled { GraphicsMode::BG3_MASK } else { 0 };
         let sprites = if self.sprites_enabled { GraphicsMode::SPRITES_MASK } else { 0 };
         let window0 = if self.window0_enabled { GraphicsMode::WINDOW0_MASK } else { 0 };
-        let window1= if self.window1_enabled { GraphicsMode::WINDOW1_MASK } else { 0 };
+        let window1 = if self.window1_enabled { GraphicsMode::WINDOW1_MASK } else { 0 };
         let sprite_windows = if self.sprite_windows_enabled { GraphicsMode::SPRITE_WINDOWS_MASK} else { 0 };
 
-        reg |= bg0 | bg1 | bg2 | bg3 | sprites | window0 | window1 | sprite_windows;
+        reg |= cgb | bg0 | bg1 | bg2 | bg3 | sprites | window0 | window1 | sprite_windows;
+        reg
+    }
 
+    pub fn set(&self) {
         // The * mut _ is to prevent a weird warning, that may be a bug in rustc
         // when using reg::REG_GRAPHICS_MODE.ptr_mut, a warning as thrown that says:
         //
@@ -192,7 +241,15 @@ impl GraphicsMode {
         //    |
         //    = note: #[warn(const_err)] on by default
 
-        unsafe { volatile_store(reg::REG_GRAPHICS_MODE.num as * mut _, reg) }
+        unsafe { volatile_store(reg::REG_GRAPHICS_MODE.num as * mut _, self.to_u16()) }
+    }
+
+    /// Reads DISPCNT, lets `f` change it, and writes it back, leaving every field `f` doesn't
+    /// touch as it was.
+    pub fn modify<F: FnOnce(&mut GraphicsMode)>(f: F) {
+        let mut mode = GraphicsMode::current();
+        f(&mut mode);
+        mode.set();
     }
 
 }
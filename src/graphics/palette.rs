@@ -0,0 +1,25 @@
+use collections::StaticArr;
+use ptr::Ptr;
+use graphics::sprites::PALETTE_MEMORY;
+
+/// The 256 color background palette. The sprite palette lives right after it, see
+/// `PALETTE_MEMORY`.
+pub(crate) const BG_PALETTE_MEMORY: StaticArr<u16> = unsafe { StaticArr::new(Ptr::from_u32(0x05000000), 256) };
+
+/// Exclusive handle to palette RAM, which holds both the background and the sprite palettes.
+pub struct Palettes {
+    bg: StaticArr<u16>,
+    obj: StaticArr<u16>,
+}
+
+impl Palettes {
+    pub(crate) const unsafe fn new() -> Self {
+        Palettes { bg: BG_PALETTE_MEMORY, obj: PALETTE_MEMORY }
+    }
+
+    /// The background palette: 256 colors, or 16 banks of 16 colors in 4bpp mode.
+    pub fn bg(&mut self) -> &mut StaticArr<u16> { &mut self.bg }
+
+    /// The sprite palette: 256 colors, or 16 banks of 16 colors in 4bpp mode.
+    pub fn obj(&mut self) -> &mut StaticArr<u16> { &mut self.obj }
+}
@@ -0,0 +1,169 @@
+//! Raster splits driven by the VCount interrupt: a list of (line, action) pairs, where every action
+//! runs when the display reaches its line. Changing scroll, video mode or palette from an action
+//! gives e.g. a status bar that looks different from the playfield, without an HBlank DMA table.
+//!
+//! The VCount match happens when a line starts being drawn, which is too late to change how it
+//! looks. So every split is armed on the line before its own, and its action waits for that line's
+//! HBlank before it runs.
+//!
+//! The splits are re-armed automatically: after the last split of a frame runs, the VCount setting
+//! goes back to the first one. Calling `vblank` once per frame also puts the cycle back at the first
+//! split, in case a match was ever missed.
+use core::cell::RefCell;
+use core::intrinsics::{ volatile_load, volatile_store };
+use interrupt::{ self, Irq, Mutex };
+use reg;
+
+/// The maximum number of splits per frame.
+pub const MAX_SPLITS: usize = 16;
+
+/// The number of scanlines in a frame, including the ones in VBlank.
+pub const TOTAL_SCANLINES: u16 = 228;
+
+/// The action run when a split's line is reached. It runs inside the VCount interrupt, during the
+/// HBlank before the line is drawn, so it should be short: the HBlank only lasts about 1000 cycles.
+pub type SplitAction = fn();
+
+/// The reasons a list of splits can be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SplitError {
+    /// There are more than `MAX_SPLITS` splits.
+    TooMany,
+    /// The splits aren't in increasing order of line.
+    Unsorted,
+    /// A split's line is past the last scanline (227).
+    BadLine,
+}
+
+#[derive(Copy, Clone)]
+struct Split {
+    line: u16,
+    action: Option<SplitAction>,
+}
+
+struct Splits {
+    list: [Split; MAX_SPLITS],
+    len: usize,
+    /// The split the VCount setting currently points at
+    next: usize,
+}
+
+static SPLITS: Mutex<RefCell<Splits>> = Mutex::new(RefCell::new(Splits {
+    list: [Split { line: 0, action: None }; MAX_SPLITS],
+    len: 0,
+    next: 0,
+}));
+
+const DISPSTAT_VCOUNT_MASK: u16 = 0xFF00;
+const DISPSTAT_HBLANK_MASK: u16 = 0x0002;
+
+/// The line a split has to be armed on: the one before it, so its action can run in that line's
+/// HBlank. Line 0 is preceded by the last line of VBlank.
+fn arm_line(line: u16) -> u16 {
+    (line + TOTAL_SCANLINES - 1) % TOTAL_SCANLINES
+}
+
+/// Waits until `line` reaches its HBlank, or has already been left.
+fn wait_for_hblank(line: u16) {
+    unsafe {
+        while volatile_load(reg::REG_VCOUNT.num as * const u16) == line
+            && volatile_load(reg::REG_DISPSTAT.num as * const u16) & DISPSTAT_HBLANK_MASK == 0 {}
+    }
+}
+
+/// Sets the line the VCount interrupt (and the VCount match flag in DISPSTAT) triggers on.
+pub fn set_vcount_setting(line: u16) {
+    interrupt::free(|_| unsafe {
+        let p = reg::REG_DISPSTAT.num as * mut u16;
+        volatile_store(p, (volatile_load(p) & !DISPSTAT_VCOUNT_MASK) | ((line & 0xFF) << 8));
+    })
+}
+
+pub fn vcount_setting() -> u16 {
+    unsafe { volatile_load(reg::REG_DISPSTAT.num as * const u16) >> 8 }
+}
+
+/// Replaces the list of splits. The splits must be in increasing order of line. If the splits are
+/// running, the new list takes effect right away, starting with the first split.
+pub fn set_splits(splits: &[(u16, SplitAction)]) -> Result<(), SplitError> {
+    if splits.len() > MAX_SPLITS {
+        return Err(SplitError::TooMany);
+    }
+    let mut prev = None;
+    for &(line, _) in splits {
+        if line >= TOTAL_SCANLINES {
+            return Err(SplitError::BadLine);
+        }
+        if let Some(prev) = prev {
+            if line <= prev {
+                return Err(SplitError::Unsorted);
+            }
+        }
+        prev = Some(line);
+    }
+
+    interrupt::free(|cs| {
+        let mut s = SPLITS.borrow(cs).borrow_mut();
+        for (i, &(line, action)) in splits.iter().enumerate() {
+            s.list[i] = Split { line, action: Some(action) };
+        }
+        s.len = splits.len();
+        s.next = 0;
+        if s.len != 0 {
+            set_vcount_setting(arm_line(s.list[0].line));
+        }
+    });
+    Ok(())
+}
+
+/// Runs the split the VCount setting points at, then points it at the next one.
+fn on_vcount() {
+    let split = interrupt::free(|cs| {
+        let mut s = SPLITS.borrow(cs).borrow_mut();
+        if s.len == 0 {
+            return None;
+        }
+        // If a match was missed, `next` is behind; the split armed on this line is the one to run
+        let vcount = unsafe { volatile_load(reg::REG_VCOUNT.num as * const u16) };
+        if let Some(i) = (0..s.len).find(|&i| arm_line(s.list[i].line) == vcount) {
+            s.next = i;
+        }
+        let current = s.list[s.next];
+        s.next = (s.next + 1) % s.len;
+        set_vcount_setting(arm_line(s.list[s.next].line));
+        Some(current)
+    });
+    if let Some(split) = split {
+        wait_for_hblank(arm_line(split.line));
+        if let Some(action) = split.action {
+            action();
+        }
+    }
+}
+
+/// Points the VCount setting back at the first split. Should be called once per frame at the start
+/// of VBlank (ideally from the VBlank interrupt), so a missed match can't put the splits out of step
+/// for more than one frame. Splits on VBlank lines that haven't run yet are skipped for the frame.
+pub fn vblank() {
+    interrupt::free(|cs| {
+        let mut s = SPLITS.borrow(cs).borrow_mut();
+        s.next = 0;
+        if s.len != 0 {
+            set_vcount_setting(arm_line(s.list[0].line));
+        }
+    })
+}
+
+/// Registers the VCount interrupt handler and enables the interrupt. `interrupt::init` has to have
+/// been called.
+pub fn start() {
+    vblank();
+    interrupt::set_handler(Irq::VCount, Some(on_vcount));
+    interrupt::enable(Irq::VCount);
+}
+
+/// Disables the VCount interrupt and removes its handler. The list of splits is kept.
+pub fn stop() {
+    interrupt::disable(Irq::VCount);
+    interrupt::set_handler(Irq::VCount, None);
+}
@@ -0,0 +1,185 @@
+//! Per-scanline effects, driven by a repeating HBlank DMA. A table with a value for each visible
+//! line is streamed into a single register, one entry per HBlank, which is what parallax
+//! scrolling, heat-haze waves and split-screen HUDs are made of.
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/dma.htm#sec-demo
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::intrinsics::volatile_store;
+use dma::{ DmaChannel, DmaControl, DmaError, DmaUnit, DestAddrControl, StartTiming };
+use graphics::tiled_bg::Background;
+use reg;
+
+/// The number of visible scanlines.
+pub const SCANLINES: usize = 160;
+
+/// A value for each visible line, plus one that is read by the HBlank after the last line. That
+/// extra entry is written while the screen is in VBlank, so it is never seen; repeating the entry
+/// for line 0 avoids a visible change on the first line if `vblank` is ever late.
+pub type ScanlineTable<T> = [T; SCANLINES + 1];
+
+/// The 2x2 matrix of an affine background (BGxPA - BGxPD), in 8.8 fixed point.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AffineParams {
+    pub pa: i16,
+    pub pb: i16,
+    pub pc: i16,
+    pub pd: i16,
+}
+
+/// The reference point of an affine background (BGxX, BGxY), in 20.8 fixed point.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct AffineRef {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A value that can be written to a register once per scanline.
+pub trait ScanlineValue: Copy {
+    /// The widest DMA unit that evenly divides the value.
+    const UNIT: DmaUnit;
+}
+
+impl ScanlineValue for u16 { const UNIT: DmaUnit = DmaUnit::_16bit; }
+impl ScanlineValue for i16 { const UNIT: DmaUnit = DmaUnit::_16bit; }
+impl ScanlineValue for u32 { const UNIT: DmaUnit = DmaUnit::_32bit; }
+impl ScanlineValue for AffineParams { const UNIT: DmaUnit = DmaUnit::_32bit; }
+impl ScanlineValue for AffineRef { const UNIT: DmaUnit = DmaUnit::_32bit; }
+
+/// The register (or group of adjacent registers) a scanline effect writes to. The type parameter
+/// is the type of a single table entry.
+#[derive(Copy, Clone)]
+pub struct ScanlineTarget<T: ScanlineValue> {
+    addr: u32,
+    _value: PhantomData<T>,
+}
+
+impl<T: ScanlineValue> ScanlineTarget<T> {
+    const unsafe fn new(addr: u32) -> Self {
+        ScanlineTarget { addr, _value: PhantomData }
+    }
+}
+
+impl ScanlineTarget<i16> {
+    /// The horizontal offset (BGxHOFS) of a regular background.
+    pub fn bg_x(bg: &Background) -> Self {
+        unsafe { ScanlineTarget::new(reg::REG_BG_OFS.num + bg.index() * 4) }
+    }
+
+    /// The vertical offset (BGxVOFS) of a regular background.
+    pub fn bg_y(bg: &Background) -> Self {
+        unsafe { ScanlineTarget::new(reg::REG_BG_VOFS.num + bg.index() * 4) }
+    }
+}
+
+impl ScanlineTarget<u32> {
+    /// Both offsets of a regular background at once: x in the low halfword, y in the high one.
+    pub fn bg_offset(bg: &Background) -> Self {
+        unsafe { ScanlineTarget::new(reg::REG_BG_OFS.num + bg.index() * 4) }
+    }
+}
+
+impl ScanlineTarget<AffineParams> {
+    /// The affine matrix of background 2 or 3; `None` for backgrounds 0 and 1, which can't be
+    /// affine.
+    pub fn bg_affine(bg: &Background) -> Option<Self> {
+        affine_index(bg).map(|n| unsafe { ScanlineTarget::new(reg::REG_BG_AFFINE.num + n * 0x10) })
+    }
+}
+
+impl ScanlineTarget<AffineRef> {
+    /// The reference point of background 2 or 3; `None` for backgrounds 0 and 1, which can't be
+    /// affine.
+    pub fn bg_affine_ref(bg: &Background) -> Option<Self> {
+        affine_index(bg).map(|n| unsafe { ScanlineTarget::new(reg::REG_BG_AFFINE.num + 8 + n * 0x10) })
+    }
+}
+
+/// Which of the two affine backgrounds `bg` is: 0 for background 2, 1 for background 3.
+fn affine_index(bg: &Background) -> Option<u32> {
+    match bg.index() {
+        2 => Some(0),
+        3 => Some(1),
+        _ => None,
+    }
+}
+
+impl ScanlineTarget<u16> {
+    /// The horizontal bounds of window 0 or 1 (WINxH): the right edge in the low byte and the left
+    /// edge in the high byte. n = n (mod 2)
+    pub fn window_x(n: u32) -> Self {
+        unsafe { ScanlineTarget::new(reg::REG_WINDOW_H.num + (n & 1) * 2) }
+    }
+
+    /// The vertical bounds of window 0 or 1 (WINxV): the bottom edge in the low byte and the top
+    /// edge in the high byte. n = n (mod 2)
+    pub fn window_y(n: u32) -> Self {
+        unsafe { ScanlineTarget::new(reg::REG_WINDOW_V.num + (n & 1) * 2) }
+    }
+}
+
+/// Streams a table of per-scanline values into a register using a repeating HBlank DMA. DMA0 is
+/// the best channel for this, since it has the highest priority and won't be delayed by other
+/// transfers, but it can't read ROM: with DMA0 the table has to be in RAM.
+///
+/// `vblank` has to be called once every frame during VBlank (ideally from the VBlank interrupt) to
+/// rewind the transfer to the start of the table.
+///
+/// Entry n is written during the HBlank before line n is drawn; since HBlank happens after a line
+/// is drawn, entry 0 is written by `vblank` and the HBlank after the last line reads the extra
+/// entry at the end of the table (see `ScanlineTable`).
+pub struct ScanlineEffect<C: DmaChannel, T: ScanlineValue> {
+    dma: C,
+    target: ScanlineTarget<T>,
+    table: &'static ScanlineTable<T>,
+}
+
+impl<C: DmaChannel, T: ScanlineValue> ScanlineEffect<C, T> {
+
+    /// Creates the effect. Nothing is written until the first call to `vblank`.
+    pub fn new(dma: C, target: ScanlineTarget<T>, table: &'static ScanlineTable<T>) -> Self {
+        ScanlineEffect { dma, target, table }
+    }
+
+    /// Switches to a different table, starting with the next frame. Double buffering two tables
+    /// this way avoids tearing when a table is updated every frame.
+    pub fn set_table(&mut self, table: &'static ScanlineTable<T>) {
+        self.table = table;
+    }
+
+    /// Restarts the transfer at the start of the table. Must be called once per frame, during
+    /// VBlank. Fails if the channel can't read the table.
+    pub fn vblank(&mut self) -> Result<(), DmaError> {
+        let unit = T::UNIT;
+        let units = size_of::<T>() as u32 / unit.bytes();
+        let src = self.table.as_ptr() as u32;
+        let dst = self.target.addr;
+
+        self.dma.stop();
+        unsafe {
+            // Line 0 has no HBlank before it, so it is written right away
+            for i in 0..units {
+                match unit {
+                    DmaUnit::_16bit => volatile_store((dst + i * 2) as * mut u16, *((src + i * 2) as * const u16)),
+                    DmaUnit::_32bit => volatile_store((dst + i * 4) as * mut u32, *((src + i * 4) as * const u32)),
+                }
+            }
+
+            let mut control = DmaControl::new();
+            control.set_dest_control(DestAddrControl::IncrementReload)
+                .set_repeat(true)
+                .set_unit(unit)
+                .set_start_timing(StartTiming::HBlank);
+            self.dma.start(src + size_of::<T>() as u32, dst, units, control)
+        }
+    }
+
+    /// Stops the effect, giving the DMA channel back. The target register keeps whatever value it
+    /// was last given.
+    pub fn stop(mut self) -> C {
+        self.dma.stop();
+        self.dma
+    }
+}
@@ -2,22 +2,46 @@ use core::mem::transmute;
 use collections::StaticArr;
 use ptr::Ptr;
 use graphics::*;
+use bios::{ self, SetMode };
 
 /// Further documentation sprite related gba things can be found here: https://www.cs.rit.edu/~tjh8300/CowBite/CowBiteSpec.htm#Graphics%20Hardware%20Overview
 /// and also here: https://www.coranac.com/tonc/text/regobj.htm
 
 
-pub const OBJECT_SPRITE_ATTRIBUTES: StaticArr<SpriteAttributes> = unsafe { StaticArr::new(Ptr::from_u32(0x07000000), 128) };
-pub const OBJECT_SPRITE_AFFINE: StaticArr<SpriteAttributes> = unsafe { StaticArr::new(Ptr::from_u32(0x07000000), 32) };
+pub(crate) const OBJECT_SPRITE_ATTRIBUTES: StaticArr<SpriteAttributes> = unsafe { StaticArr::new(Ptr::from_u32(0x07000000), 128) };
+pub(crate) const OBJECT_SPRITE_AFFINE: StaticArr<SpriteAttributes> = unsafe { StaticArr::new(Ptr::from_u32(0x07000000), 32) };
 
 pub type Charblock = [u8; 0x4000];
 pub const TILE_MEMORY: StaticArr<Charblock> = unsafe { StaticArr::new(Ptr::from_u32(0x06000000), 6) };
-pub const PALETTE_MEMORY: StaticArr<u16> = unsafe { StaticArr::new(Ptr::from_u32(0x05000200), 256) };
+pub(crate) const PALETTE_MEMORY: StaticArr<u16> = unsafe { StaticArr::new(Ptr::from_u32(0x05000200), 256) };
+
+/// Clears every slot in OAM. This bypasses the `Oam` handle, so it is only safe to call when
+/// nothing else is using OAM; prefer `Oam::clear`.
+pub unsafe fn oam_clear() {
+    // OAM is 1KB, a multiple of the 32 bytes CpuFastSet moves per iteration
+    let zero = 0u32;
+    bios::cpu_fast_set(&zero as * const u32 as u32, OBJECT_SPRITE_ATTRIBUTES.as_ptr().num, 256, SetMode::Fill);
+}
+
+/// Exclusive handle to object attribute memory (OAM), which holds the attributes of all 128
+/// sprites.
+pub struct Oam { _private: () }
+
+impl Oam {
+    pub(crate) const unsafe fn new() -> Self { Oam { _private: () } }
+
+    /// Copies `attributes` into the n'th SA slot. n = n (mod 128)
+    pub fn set(&mut self, n: u32, attributes: SpriteAttributes) {
+        unsafe { attributes.set(n) }
+    }
+
+    /// Returns a copy of the attributes in the n'th SA slot. n = n (mod 128)
+    pub fn get(&self, n: u32) -> SpriteAttributes {
+        OBJECT_SPRITE_ATTRIBUTES[(n & 127) as i32]
+    }
 
-pub fn oam_clear() {
-    let p = SpriteAttributes::default();
-    for i in 0..128 {
-        p.set(i);
+    pub fn clear(&mut self) {
+        unsafe { oam_clear() }
     }
 }
 
@@ -154,9 +178,10 @@ impl SpriteAttributes {
     const PRIORITY_MASK: u16            = 0x0C00_u16;
     const PALETTE_BANK_INDEX_MASK: u16  = 0xF000_u16;
 
-    /// Copy this SpriteAttributes into the n'th SA slot.
-    pub fn set(self, n: u32) {
-        OBJECT_SPRITE_ATTRIBUTES[n as i32] = self;
+    /// Copy this SpriteAttributes into the n'th SA slot. This bypasses the `Oam` handle, so it is
+    /// only safe to call when nothing else is using OAM; prefer `Oam::set`. n = n (mod 128)
+    pub unsafe fn set(self, n: u32) {
+        OBJECT_SPRITE_ATTRIBUTES[(n & 127) as i32] = self;
     }
 
     pub fn default() -> Self { SpriteAttributes { a0: 0, a1: 0, a2: 0, filler: 0 } }
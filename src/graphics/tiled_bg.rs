@@ -10,10 +10,11 @@ pub struct BgControl(u16);
 impl BgControl {
 
     /// Returns the n'th background control. There are only 4, so only values of n [0,3]are
-    /// valid.
-    pub fn get(mut n: u32) -> &'static mut BgControl {
+    /// valid. This bypasses the `Background` handles, so it is only safe to call when nothing else
+    /// is using the background; prefer `Background::control`.
+    pub unsafe fn get(mut n: u32) -> &'static mut BgControl {
         n &= 3;
-        unsafe { reg::REG_BGCNT.transmute::<BgControl>().offset(n as i32).as_mut() }
+        reg::REG_BGCNT.transmute::<BgControl>().offset(n as i32).as_mut()
     }
 
     pub fn set_priority(&mut self, mut priority: u16) -> &mut Self {
@@ -102,19 +103,23 @@ struct BgOffsetInternal { x: i16, y: i16 }
 /// normal arithmetic operators like +=).
 ///
 /// The x and y coordinates of the background offset will be `mod mapsize`.
-#[derive(Clone)]
-struct BgOffset {
+///
+/// Not `Clone`: a copy would write the same registers while caching its own x and y.
+pub struct BgOffset {
     x: i16,
     y: i16,
     inner: Ptr<BgOffsetInternal>,
 }
 
 impl BgOffset {
-    pub fn get(x: i16, y: i16, mut n: u32) -> BgOffset {
+    /// Returns a new handle to the n'th background offset. This bypasses the `Background`
+    /// handles, so it is only safe to call when nothing else is using the background; prefer
+    /// `Background::offset`.
+    pub unsafe fn get(x: i16, y: i16, mut n: u32) -> BgOffset {
         n &= 3;
         BgOffset {
             x, y,
-            inner: unsafe { reg::REG_BG_OFS.transmute::<BgOffsetInternal>().offset(transmute(n)).transmute() },
+            inner: reg::REG_BG_OFS.transmute::<BgOffsetInternal>().offset(transmute(n)).transmute(),
         }
     }
 
@@ -179,6 +184,30 @@ impl BgOffset {
     }
 }
 
+/// Exclusive handle to one of the 4 backgrounds, owning both its control register and its offset
+/// registers.
+pub struct Background {
+    n: u32,
+    offset: BgOffset,
+}
+
+impl Background {
+    pub(crate) unsafe fn new(n: u32) -> Self {
+        Background { n, offset: BgOffset::get(0, 0, n) }
+    }
+
+    /// Which of the 4 backgrounds this is.
+    pub fn index(&self) -> u32 { self.n }
+
+    pub fn control(&mut self) -> &mut BgControl {
+        unsafe { BgControl::get(self.n) }
+    }
+
+    pub fn offset(&mut self) -> &mut BgOffset {
+        &mut self.offset
+    }
+}
+
 /// Holds metadata about a tile at a position. The position of the tile is determined by the
 /// TileEntry's location (index) in the Screenblock array.
 pub struct TileEntry(u16);
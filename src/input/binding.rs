@@ -0,0 +1,134 @@
+//! A layer between the physical keys and a game's actions, so that controls can be remapped from an
+//! options menu. The game defines its own action enum, implements `Action` for it, and asks about
+//! actions instead of keys:
+//!
+//! ```ignore
+//! #[derive(Copy, Clone)]
+//! enum Action { Jump, Attack, Menu }
+//!
+//! impl input::binding::Action for Action {
+//!     fn index(self) -> usize { self as usize }
+//! }
+//! ```
+use core::marker::PhantomData;
+use input::{ Input, Key, KeySet, ALL_KEYS };
+
+/// The number of actions a set of bindings can hold.
+pub const MAX_ACTIONS: usize = 16;
+
+/// The size of serialized bindings, in bytes: the `KeySet` of every action as a little endian u16.
+pub const SERIALIZED_LEN: usize = MAX_ACTIONS * 2;
+
+/// A game action that keys can be bound to.
+pub trait Action: Copy {
+    /// A number that is unique to this action, [0, MAX_ACTIONS).
+    fn index(self) -> usize;
+}
+
+/// The reasons bindings can't be serialized or deserialized.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BindingError {
+    /// The buffer is shorter than `SERIALIZED_LEN`.
+    TooShort,
+    /// The data has bits that don't belong to any key, so it isn't serialized bindings.
+    Corrupt,
+}
+
+/// The keys bound to every action. An action is active when any of its keys is.
+#[derive(Copy, Clone)]
+pub struct Bindings<A: Action> {
+    keys: [KeySet; MAX_ACTIONS],
+    _action: PhantomData<A>,
+}
+
+impl<A: Action> Bindings<A> {
+    /// Bindings with no keys bound to any action.
+    pub fn new() -> Self {
+        Bindings { keys: [KeySet::empty(); MAX_ACTIONS], _action: PhantomData }
+    }
+
+    /// Binds exactly `keys` to `action`, replacing its current keys.
+    pub fn bind<T: Into<KeySet>>(&mut self, action: A, keys: T) -> &mut Self {
+        self.keys[action.index()] = keys.into() & KeySet::all();
+        self
+    }
+
+    /// Binds `key` to `action`, on top of its current keys.
+    pub fn add(&mut self, action: A, key: Key) -> &mut Self {
+        let i = action.index();
+        self.keys[i] = self.keys[i].add(key);
+        self
+    }
+
+    /// Unbinds `key` from `action`.
+    pub fn remove(&mut self, action: A, key: Key) -> &mut Self {
+        let i = action.index();
+        self.keys[i] = self.keys[i].remove(key);
+        self
+    }
+
+    /// Unbinds `key` from every action, e.g. before giving it to a single action.
+    pub fn remove_everywhere(&mut self, key: Key) -> &mut Self {
+        for keys in self.keys.iter_mut() {
+            *keys = keys.remove(key);
+        }
+        self
+    }
+
+    /// The keys bound to `action`.
+    pub fn keys(&self, action: A) -> KeySet {
+        self.keys[action.index()]
+    }
+
+    /// Whether any key bound to `action` is down this frame.
+    pub fn action_pressed(&self, input: &Input, action: A) -> bool {
+        !(input.held_keys() & self.keys(action)).is_empty()
+    }
+
+    /// Whether `action` became active this frame: one of its keys is down, and none of them were
+    /// down last frame.
+    pub fn action_just_pressed(&self, input: &Input, action: A) -> bool {
+        let keys = self.keys(action);
+        !(input.pressed_keys() & keys).is_empty() && (previous_keys(input) & keys).is_empty()
+    }
+
+    /// Whether `action` stopped being active this frame: none of its keys are down, but one of
+    /// them was down last frame.
+    pub fn action_just_released(&self, input: &Input, action: A) -> bool {
+        let keys = self.keys(action);
+        (input.held_keys() & keys).is_empty() && !(input.released_keys() & keys).is_empty()
+    }
+
+    /// Writes the bindings to the first `SERIALIZED_LEN` bytes of `out`, e.g. in save memory.
+    pub fn serialize(&self, out: &mut [u8]) -> Result<(), BindingError> {
+        if out.len() < SERIALIZED_LEN {
+            return Err(BindingError::TooShort);
+        }
+        for (i, keys) in self.keys.iter().enumerate() {
+            out[i * 2] = keys.0 as u8;
+            out[i * 2 + 1] = (keys.0 >> 8) as u8;
+        }
+        Ok(())
+    }
+
+    /// Reads bindings written by `serialize`.
+    pub fn deserialize(data: &[u8]) -> Result<Self, BindingError> {
+        if data.len() < SERIALIZED_LEN {
+            return Err(BindingError::TooShort);
+        }
+        let mut bindings = Bindings::new();
+        for (i, keys) in bindings.keys.iter_mut().enumerate() {
+            let bits = data[i * 2] as u16 | ((data[i * 2 + 1] as u16) << 8);
+            if bits & !ALL_KEYS.0 != 0 {
+                return Err(BindingError::Corrupt);
+            }
+            *keys = KeySet(bits);
+        }
+        Ok(bindings)
+    }
+}
+
+/// The keys that were down on the frame before the tracker's current one.
+fn previous_keys(input: &Input) -> KeySet {
+    input.held_keys().difference(input.pressed_keys()) | input.released_keys()
+}
@@ -0,0 +1,156 @@
+//! Recognizes button combos and timed input sequences: soft-reset chords like A+B+Start+Select,
+//! cheat codes, and fighting game motions like down, down-forward, forward + A.
+//!
+//! A pattern is a list of steps. A step is completed on a frame where the held keys change to
+//! exactly the keys of the step, so a diagonal is entered by rolling from one direction into the
+//! next, and a chord only completes once all of its keys are down. Releasing keys never breaks a
+//! sequence, but pressing a key that leads away from the next step starts it over, and so does
+//! taking longer than the step's window.
+use input::{ Input, KeySet, ALL_KEYS };
+
+/// The number of patterns a `ComboMatcher` can recognize at once.
+pub const MAX_PATTERNS: usize = 8;
+
+/// One step of a pattern.
+#[derive(Copy, Clone)]
+pub struct Step {
+    /// The keys that have to be held, and nothing else.
+    pub keys: KeySet,
+    /// The most frames this step may come after the previous one; 0 means no limit. Ignored for
+    /// the first step.
+    pub window: u32,
+}
+
+impl Step {
+    pub const fn new(keys: KeySet, window: u32) -> Self {
+        Step { keys, window }
+    }
+}
+
+/// Identifies a registered pattern.
+pub type PatternId = usize;
+
+/// The reasons a pattern can't be registered.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ComboError {
+    /// `MAX_PATTERNS` patterns are already registered.
+    Full,
+    /// The pattern has no steps.
+    Empty,
+}
+
+#[derive(Copy, Clone)]
+struct Pattern {
+    steps: &'static [Step],
+    /// The number of steps completed so far
+    progress: usize,
+    /// The number of frames since the last step was completed
+    frames: u32,
+}
+
+impl Pattern {
+    /// Advances the pattern by one frame, returning whether it was completed.
+    fn update(&mut self, held: KeySet, pressed: KeySet, changed: bool) -> bool {
+        self.frames = self.frames.saturating_add(1);
+        if self.progress != 0 {
+            let window = self.steps[self.progress].window;
+            if window != 0 && self.frames > window {
+                self.progress = 0;
+            }
+        }
+        if !changed {
+            return false;
+        }
+
+        let next = self.steps[self.progress].keys.0 & ALL_KEYS.0;
+        if held.0 == next {
+            self.progress += 1;
+            self.frames = 0;
+        } else if pressed.0 != 0 && held.0 & !next != 0 {
+            // A press that can't lead to the next step; see if it starts the pattern over instead
+            self.progress = 0;
+            if held.0 == self.steps[0].keys.0 & ALL_KEYS.0 {
+                self.progress = 1;
+                self.frames = 0;
+            }
+        }
+
+        if self.progress == self.steps.len() {
+            self.progress = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Recognizes up to `MAX_PATTERNS` patterns at once. `update` has to be called once per frame,
+/// after the `Input` it reads from has been updated.
+pub struct ComboMatcher {
+    patterns: [Option<Pattern>; MAX_PATTERNS],
+    /// The patterns completed on the last update, one bit per `PatternId`
+    matched: u32,
+}
+
+impl ComboMatcher {
+    pub fn new() -> Self {
+        ComboMatcher { patterns: [None; MAX_PATTERNS], matched: 0 }
+    }
+
+    /// Starts recognizing `steps`, returning the id that `matched` reports it under.
+    pub fn register(&mut self, steps: &'static [Step]) -> Result<PatternId, ComboError> {
+        if steps.is_empty() {
+            return Err(ComboError::Empty);
+        }
+        match self.patterns.iter().position(|p| p.is_none()) {
+            Some(id) => {
+                self.patterns[id] = Some(Pattern { steps, progress: 0, frames: 0 });
+                Ok(id)
+            },
+            None => Err(ComboError::Full),
+        }
+    }
+
+    /// Stops recognizing a pattern, freeing its id.
+    pub fn unregister(&mut self, id: PatternId) {
+        if id < MAX_PATTERNS {
+            self.patterns[id] = None;
+            self.matched &= !(1 << id);
+        }
+    }
+
+    /// Forgets the progress of every pattern.
+    pub fn reset(&mut self) {
+        for pattern in self.patterns.iter_mut() {
+            if let Some(ref mut pattern) = *pattern {
+                pattern.progress = 0;
+            }
+        }
+        self.matched = 0;
+    }
+
+    /// Advances every pattern by one frame.
+    pub fn update(&mut self, input: &Input) {
+        let held = input.held_keys();
+        let pressed = input.pressed_keys();
+        let changed = pressed.0 != 0 || input.released_keys().0 != 0;
+        self.matched = 0;
+        for (id, pattern) in self.patterns.iter_mut().enumerate() {
+            if let Some(ref mut pattern) = *pattern {
+                if pattern.update(held, pressed, changed) {
+                    self.matched |= 1 << id;
+                }
+            }
+        }
+    }
+
+    /// Whether pattern `id` was completed on this frame.
+    pub fn matched(&self, id: PatternId) -> bool {
+        id < MAX_PATTERNS && self.matched & (1 << id) != 0
+    }
+
+    /// The lowest id of the patterns completed on this frame, if any.
+    pub fn first_match(&self) -> Option<PatternId> {
+        if self.matched == 0 { None } else { Some(self.matched.trailing_zeros() as usize) }
+    }
+}
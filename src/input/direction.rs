@@ -0,0 +1,157 @@
+//! 8-way directions decoded from the D-pad. Vectors use screen coordinates: x grows to the right
+//! and y grows downwards, so North (Up) is (0, -1).
+use input::{ InputState, Key };
+
+/// A D-pad direction, named after the points of the compass with North being Up.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    None,
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    /// The direction of a vector, using only the signs of its components.
+    pub fn from_vector(dx: i32, dy: i32) -> Direction {
+        match (dx.signum(), dy.signum()) {
+            (0, -1) => Direction::N,
+            (1, -1) => Direction::NE,
+            (1, 0) => Direction::E,
+            (1, 1) => Direction::SE,
+            (0, 1) => Direction::S,
+            (-1, 1) => Direction::SW,
+            (-1, 0) => Direction::W,
+            (-1, -1) => Direction::NW,
+            _ => Direction::None,
+        }
+    }
+
+    /// The direction as (dx, dy), each in [-1, 1].
+    pub fn vector(self) -> (i32, i32) {
+        match self {
+            Direction::None => (0, 0),
+            Direction::N => (0, -1),
+            Direction::NE => (1, -1),
+            Direction::E => (1, 0),
+            Direction::SE => (1, 1),
+            Direction::S => (0, 1),
+            Direction::SW => (-1, 1),
+            Direction::W => (-1, 0),
+            Direction::NW => (-1, -1),
+        }
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        let (dx, dy) = self.vector();
+        dx != 0 && dy != 0
+    }
+}
+
+/// How simultaneous opposite directions (SOCD), e.g. Left and Right at the same time, are
+/// resolved. Worn D-pads can report both.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Socd {
+    /// Opposite directions cancel out.
+    Neutral,
+    /// The direction pressed most recently wins.
+    LastWins,
+    /// The direction that was held first wins.
+    FirstWins,
+}
+
+/// One axis of the D-pad: the key for -1 and the key for 1, and what they resolved to.
+#[derive(Copy, Clone)]
+struct Axis {
+    negative: bool,
+    positive: bool,
+    value: i32,
+}
+
+impl Axis {
+    const fn new() -> Self {
+        Axis { negative: false, positive: false, value: 0 }
+    }
+
+    fn update(&mut self, negative: bool, positive: bool, policy: Socd) -> i32 {
+        self.value = match (negative, positive) {
+            (false, false) => 0,
+            (true, false) => -1,
+            (false, true) => 1,
+            (true, true) => match policy {
+                Socd::Neutral => 0,
+                // Pressed on the same frame: nothing to tell them apart by
+                _ if !self.negative && !self.positive => 0,
+                // Both were already held, keep whatever was decided
+                _ if self.negative && self.positive => self.value,
+                Socd::LastWins => if self.negative { 1 } else { -1 },
+                Socd::FirstWins => if self.negative { -1 } else { 1 },
+            },
+        };
+        self.negative = negative;
+        self.positive = positive;
+        self.value
+    }
+}
+
+/// Decodes the D-pad into a direction from frame to frame, resolving opposite directions with a
+/// `Socd` policy. `update` has to be called once per frame; with `Socd::Neutral` no history is
+/// needed and `InputState::direction` can be used instead.
+#[derive(Copy, Clone)]
+pub struct Dpad {
+    policy: Socd,
+    x: Axis,
+    y: Axis,
+}
+
+impl Dpad {
+    pub const fn new(policy: Socd) -> Self {
+        Dpad { policy, x: Axis::new(), y: Axis::new() }
+    }
+
+    pub fn set_policy(&mut self, policy: Socd) {
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> Socd {
+        self.policy
+    }
+
+    /// Advances by one frame, returning the direction for `state`.
+    pub fn update(&mut self, state: InputState) -> Direction {
+        let policy = self.policy;
+        self.x.update(state.key_down(Key::Left), state.key_down(Key::Right), policy);
+        self.y.update(state.key_down(Key::Up), state.key_down(Key::Down), policy);
+        self.direction()
+    }
+
+    /// The direction as of the last `update`.
+    pub fn direction(&self) -> Direction {
+        Direction::from_vector(self.x.value, self.y.value)
+    }
+
+    /// The direction as of the last `update`, as (dx, dy).
+    pub fn vector(&self) -> (i32, i32) {
+        (self.x.value, self.y.value)
+    }
+}
+
+impl InputState {
+    /// The direction of the D-pad, with opposite directions cancelling out.
+    pub fn direction(self) -> Direction {
+        let (dx, dy) = self.vector();
+        Direction::from_vector(dx, dy)
+    }
+
+    /// The direction of the D-pad as (dx, dy), with opposite directions cancelling out.
+    pub fn vector(self) -> (i32, i32) {
+        let dx = self.key_down(Key::Right) as i32 - self.key_down(Key::Left) as i32;
+        let dy = self.key_down(Key::Down) as i32 - self.key_down(Key::Up) as i32;
+        (dx, dy)
+    }
+}
@@ -0,0 +1,96 @@
+//! The keypad interrupt (REG_KEYCNT), and a sleep mode that wakes up on it.
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/keys.htm#sec-irq
+use input::{ InputState, KeySet, ALL_KEYS };
+use interrupt::{ self, Irq };
+use bios;
+use reg;
+
+/// When the keypad interrupt is raised.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(u16)]
+pub enum KeyCondition {
+    /// As soon as any of the keys is down.
+    Any = 0x0000,
+    /// Once all of the keys are down at the same time.
+    All = 0x8000,
+}
+
+impl KeyCondition {
+    /// Whether `state` satisfies this condition for `keys`.
+    pub fn is_met(self, state: InputState, keys: KeySet) -> bool {
+        match self {
+            KeyCondition::Any => state.any_keys_down(keys),
+            KeyCondition::All => state.all_keys_down(keys),
+        }
+    }
+}
+
+const KEYCNT_IRQ_MASK: u16 = 0x4000;
+
+/// Selects the keys and condition that raise the keypad interrupt. The interrupt itself is turned
+/// on and off with `interrupt::enable(Irq::Keypad)` and `interrupt::disable(Irq::Keypad)`.
+pub fn set_key_irq(keys: KeySet, condition: KeyCondition) {
+    interrupt::free(|_| unsafe {
+        let irq = reg::REG_KEYCNT.volatile_load() & KEYCNT_IRQ_MASK;
+        reg::REG_KEYCNT.volatile_store(irq | condition as u16 | (keys.0 & ALL_KEYS.0));
+    })
+}
+
+/// The keys and condition that raise the keypad interrupt.
+pub fn key_irq() -> (KeySet, KeyCondition) {
+    let keycnt = unsafe { reg::REG_KEYCNT.volatile_load() };
+    let condition = if keycnt & KeyCondition::All as u16 != 0 { KeyCondition::All } else { KeyCondition::Any };
+    (KeySet(keycnt & ALL_KEYS.0), condition)
+}
+
+/// Waits until `keys` no longer satisfy `condition` on the real keypad.
+fn wait_for_release(keys: KeySet, condition: KeyCondition) {
+    while condition.is_met(InputState::hardware(), keys) {}
+}
+
+/// Puts the GBA into its low power Stop mode until `keys` satisfy `condition`, e.g. `All` of
+/// L+R+Select. The screen and sound are turned off while asleep, and the display and sound control
+/// registers are restored on wake up. The hardware resets the PSG channel registers when sound is
+/// turned off, so PSG sounds have to be set up again. `interrupt::init` must have been called.
+///
+/// The combo has to be released before the GBA goes to sleep, and again after it wakes up, so the
+/// keys that wake it aren't seen by the game.
+pub fn sleep(keys: KeySet, condition: KeyCondition) {
+    wait_for_release(keys, condition);
+
+    let (old_keys, old_condition) = key_irq();
+    let was_enabled = interrupt::is_enabled(Irq::Keypad);
+    set_key_irq(keys, condition);
+    interrupt::enable(Irq::Keypad);
+
+    let (dispcnt, sound_l, sound_h, sound_x) = interrupt::free(|_| unsafe {
+        let saved = (reg::REG_GRAPHICS_MODE.volatile_load(), reg::REG_SOUNDCNT_L.volatile_load(),
+                     reg::REG_SOUNDCNT_H.volatile_load(), reg::REG_SOUNDCNT_X.volatile_load());
+        // Forced blank, and the sound master enable off
+        reg::REG_GRAPHICS_MODE.volatile_store(saved.0 | 0x0080);
+        reg::REG_SOUNDCNT_X.volatile_store(0);
+        saved
+    });
+
+    // Only the keypad may wake the system up
+    interrupt::with_only(&[Irq::Keypad], || {
+        let ime = interrupt::master_enabled();
+        interrupt::set_master_enabled(true);
+        bios::stop();
+        interrupt::set_master_enabled(ime);
+    });
+
+    interrupt::free(|_| unsafe {
+        reg::REG_SOUNDCNT_X.volatile_store(sound_x);
+        reg::REG_SOUNDCNT_L.volatile_store(sound_l);
+        reg::REG_SOUNDCNT_H.volatile_store(sound_h);
+        reg::REG_GRAPHICS_MODE.volatile_store(dispcnt);
+    });
+
+    set_key_irq(old_keys, old_condition);
+    if !was_enabled {
+        interrupt::disable(Irq::Keypad);
+    }
+    wait_for_release(keys, condition);
+}
@@ -1,13 +1,51 @@
 use reg::REG_KEY_INPUT;
 use core::mem;
-use core::ops::Deref;
+use core::cell::Cell;
+use core::fmt;
+use core::hash::{ Hash, Hasher };
+use core::ops::{ BitAnd, BitOr, Deref, Not };
+use interrupt::{ self, Mutex };
+
+mod tracker;
+pub mod replay;
+pub mod combo;
+pub mod binding;
+mod keypad;
+mod direction;
+pub use self::tracker::{ Input, RepeatSettings };
+pub use self::keypad::{ KeyCondition, set_key_irq, key_irq, sleep };
+pub use self::direction::{ Direction, Socd, Dpad };
+
+/// A state that replaces the keypad, see `inject`.
+static INJECTED: Mutex<Cell<Option<u16>>> = Mutex::new(Cell::new(None));
+
+/// Makes `InputState::current` return `state` instead of reading the keypad, until this is called
+/// again with `None`. This is how recordings are played back (see `replay::Player`), and it can be
+/// used to drive game logic without a keypad.
+pub fn inject(state: Option<InputState>) {
+    interrupt::free(|cs| INJECTED.borrow(cs).set(state.map(|s| s.0)))
+}
 
 #[derive(Copy, Clone)]
 pub struct InputState(pub u16);
 
 impl InputState {
+    /// The state of the keypad, or the injected state if there is one (see `inject`).
     pub fn current() -> Self {
-        InputState(*REG_KEY_INPUT)
+        match interrupt::free(|cs| INJECTED.borrow(cs).get()) {
+            Some(state) => InputState(state),
+            None => InputState::hardware(),
+        }
+    }
+
+    /// The state of the real keypad, ignoring any injected state.
+    pub fn hardware() -> Self {
+        InputState(unsafe { REG_KEY_INPUT.volatile_load() })
+    }
+
+    /// The state in which exactly `keys` are held.
+    pub fn from_keys(keys: KeySet) -> Self {
+        InputState(!keys.0)
     }
 
     pub fn all_keys_down<T: Into<KeySet>>(self, keys: T) -> bool {
@@ -21,7 +59,7 @@ impl InputState {
     }
 
     pub fn pressed_keys(self) -> KeySet {
-        KeySet(!self.0)
+        KeySet(!self.0 & ALL_KEYS.0)
     }
 
     pub fn key_down(self, key: Key) -> bool {
@@ -29,7 +67,7 @@ impl InputState {
     }
 }
 
-/// A set of keys
+/// A set of keys. Bits that don't belong to a key are ignored, including by comparisons.
 #[derive(Copy, Clone)]
 pub struct KeySet(pub u16);
 
@@ -49,13 +87,127 @@ impl KeySet {
     }
 
     pub fn add(mut self, key: Key) -> Self {
-        self.0 |= *key;
+        self.0 = (self.0 | *key) & ALL_KEYS.0;
+        self
+    }
+
+    pub fn remove(mut self, key: Key) -> Self {
+        self.0 &= !*key & ALL_KEYS.0;
         self
     }
+
+    /// The keys in either set.
+    pub fn union<T: Into<KeySet>>(self, other: T) -> Self {
+        KeySet((self.0 | other.into().0) & ALL_KEYS.0)
+    }
+
+    /// The keys in both sets.
+    pub fn intersection<T: Into<KeySet>>(self, other: T) -> Self {
+        KeySet(self.0 & other.into().0 & ALL_KEYS.0)
+    }
+
+    /// The keys in this set, but not in `other`.
+    pub fn difference<T: Into<KeySet>>(self, other: T) -> Self {
+        KeySet(self.0 & !other.into().0 & ALL_KEYS.0)
+    }
+
+    /// The keys that aren't in this set. Bits that don't belong to a key are never set.
+    pub fn complement(self) -> Self {
+        KeySet(!self.0 & ALL_KEYS.0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 & ALL_KEYS.0 == 0
+    }
+
+    /// The number of keys in the set.
+    pub fn len(self) -> usize {
+        (self.0 & ALL_KEYS.0).count_ones() as usize
+    }
+
+    /// The keys in the set, in the order of their bits.
+    pub fn iter(self) -> KeySetIter {
+        KeySetIter(self.0 & ALL_KEYS.0)
+    }
 }
 
-#[repr(u16)]
+impl IntoIterator for KeySet {
+    type Item = Key;
+    type IntoIter = KeySetIter;
+
+    fn into_iter(self) -> KeySetIter {
+        self.iter()
+    }
+}
+
+/// Iterates over the keys in a `KeySet`, see `KeySet::iter`.
 #[derive(Copy, Clone)]
+pub struct KeySetIter(u16);
+
+impl Iterator for KeySetIter {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Key> {
+        if self.0 == 0 {
+            return None;
+        }
+        let key = Key::ALL[self.0.trailing_zeros() as usize];
+        self.0 &= self.0 - 1;
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl PartialEq for KeySet {
+    fn eq(&self, other: &KeySet) -> bool {
+        self.0 & ALL_KEYS.0 == other.0 & ALL_KEYS.0
+    }
+}
+
+impl Eq for KeySet {}
+
+impl Hash for KeySet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 & ALL_KEYS.0).hash(state)
+    }
+}
+
+impl fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Into<KeySet>> BitOr<T> for KeySet {
+    type Output = KeySet;
+
+    fn bitor(self, rhs: T) -> KeySet {
+        self.union(rhs)
+    }
+}
+
+impl<T: Into<KeySet>> BitAnd<T> for KeySet {
+    type Output = KeySet;
+
+    fn bitand(self, rhs: T) -> KeySet {
+        self.intersection(rhs)
+    }
+}
+
+impl Not for KeySet {
+    type Output = KeySet;
+
+    fn not(self) -> KeySet {
+        self.complement()
+    }
+}
+
+#[repr(u16)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Key {
     A =      0x0001,
     B =      0x0002,
@@ -69,6 +221,19 @@ pub enum Key {
     L      = 0x0200,
 }
 
+impl Key {
+    /// Every key, in the order of their bits.
+    pub const ALL: [Key; 10] = [
+        Key::A, Key::B, Key::Select, Key::Start, Key::Right, Key::Left, Key::Up, Key::Down, Key::R,
+        Key::L,
+    ];
+
+    /// The position of this key's bit in REG_KEY_INPUT, [0, 9].
+    pub fn index(self) -> usize {
+        (*self).trailing_zeros() as usize
+    }
+}
+
 impl Deref for Key {
     type Target = u16;
 
@@ -81,4 +246,28 @@ impl Into<KeySet> for Key {
     fn into(self) -> KeySet {
         KeySet(*self)
     }
-}
\ No newline at end of file
+}
+
+impl<T: Into<KeySet>> BitOr<T> for Key {
+    type Output = KeySet;
+
+    fn bitor(self, rhs: T) -> KeySet {
+        KeySet(*self).union(rhs)
+    }
+}
+
+impl<T: Into<KeySet>> BitAnd<T> for Key {
+    type Output = KeySet;
+
+    fn bitand(self, rhs: T) -> KeySet {
+        KeySet(*self).intersection(rhs)
+    }
+}
+
+impl Not for Key {
+    type Output = KeySet;
+
+    fn not(self) -> KeySet {
+        KeySet(*self).complement()
+    }
+}
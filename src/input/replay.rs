@@ -0,0 +1,127 @@
+//! Deterministic input recording and playback. A recording is a run length encoded stream of the
+//! keys held on every frame: 4 bytes per run, the `KeySet` followed by the number of frames it was
+//! held for (both little endian u16s). It can live in any byte buffer, e.g. in EWRAM or save
+//! memory.
+//!
+//! Played back recordings are injected behind `InputState::current`, so game code reads them
+//! exactly like the real keypad. That makes attract mode demos, bug reproduction and tests of game
+//! logic possible.
+use input::{ self, InputState, KeySet, ALL_KEYS };
+
+/// The size of one run in a recording, in bytes.
+pub const RUN_LEN: usize = 4;
+
+/// The reasons a recording can fail.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ReplayError {
+    /// The buffer is full.
+    Full,
+}
+
+/// Records the keys held on every frame into a buffer.
+pub struct Recorder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    keys: KeySet,
+    run: u16,
+}
+
+impl<'a> Recorder<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Recorder { buf, len: 0, keys: KeySet::empty(), run: 0 }
+    }
+
+    /// Adds a frame on which `keys` were held.
+    pub fn record(&mut self, keys: KeySet) -> Result<(), ReplayError> {
+        let keys = KeySet(keys.0 & ALL_KEYS.0);
+        if self.run != 0 && self.run != u16::max_value() && keys.0 == self.keys.0 {
+            self.run += 1;
+            return Ok(());
+        }
+        self.flush()?;
+        self.keys = keys;
+        self.run = 1;
+        Ok(())
+    }
+
+    /// Adds a frame with the keys currently held on the real keypad, ignoring anything that is
+    /// being played back.
+    pub fn record_frame(&mut self) -> Result<(), ReplayError> {
+        self.record(InputState::hardware().pressed_keys())
+    }
+
+    /// Writes out the run in progress.
+    fn flush(&mut self) -> Result<(), ReplayError> {
+        if self.run == 0 {
+            return Ok(());
+        }
+        if self.len + RUN_LEN > self.buf.len() {
+            return Err(ReplayError::Full);
+        }
+        let (keys, run) = (self.keys.0, self.run);
+        self.buf[self.len] = keys as u8;
+        self.buf[self.len + 1] = (keys >> 8) as u8;
+        self.buf[self.len + 2] = run as u8;
+        self.buf[self.len + 3] = (run >> 8) as u8;
+        self.len += RUN_LEN;
+        self.run = 0;
+        Ok(())
+    }
+
+    /// Finishes the recording, returning the number of bytes of the buffer it uses.
+    pub fn finish(mut self) -> Result<usize, ReplayError> {
+        self.flush()?;
+        Ok(self.len)
+    }
+}
+
+/// Plays a recording back, one frame at a time.
+pub struct Player<'a> {
+    data: &'a [u8],
+    pos: usize,
+    keys: KeySet,
+    run: u16,
+}
+
+impl<'a> Player<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Player { data, pos: 0, keys: KeySet::empty(), run: 0 }
+    }
+
+    /// The keys held on the next frame of the recording, or `None` at the end of it.
+    pub fn next_frame(&mut self) -> Option<KeySet> {
+        while self.run == 0 {
+            if self.pos + RUN_LEN > self.data.len() {
+                return None;
+            }
+            let d = &self.data[self.pos..self.pos + RUN_LEN];
+            self.keys = KeySet(d[0] as u16 | ((d[1] as u16) << 8));
+            self.run = d[2] as u16 | ((d[3] as u16) << 8);
+            self.pos += RUN_LEN;
+        }
+        self.run -= 1;
+        Some(self.keys)
+    }
+
+    /// Makes `InputState::current` return the next frame of the recording. At the end of the
+    /// recording the real keypad is restored and `false` is returned. Call this once per frame,
+    /// before the game reads its input.
+    pub fn play_frame(&mut self) -> bool {
+        match self.next_frame() {
+            Some(keys) => {
+                input::inject(Some(InputState::from_keys(keys)));
+                true
+            },
+            None => {
+                input::inject(None);
+                false
+            }
+        }
+    }
+
+    /// Rewinds to the start of the recording.
+    pub fn rewind(&mut self) {
+        self.pos = 0;
+        self.run = 0;
+    }
+}
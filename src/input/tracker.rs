@@ -0,0 +1,132 @@
+use input::{ InputState, Key, KeySet };
+
+/// How a held key repeats: the first repeat comes `delay` frames after the key is pressed, and
+/// then one every `interval` frames. An interval of 0 turns repeating off.
+#[derive(Copy, Clone)]
+pub struct RepeatSettings {
+    pub delay: u32,
+    pub interval: u32,
+}
+
+impl RepeatSettings {
+    /// A third of a second before the first repeat, then 15 repeats per second.
+    pub const DEFAULT: RepeatSettings = RepeatSettings { delay: 20, interval: 4 };
+
+    /// Only the initial press counts.
+    pub const NONE: RepeatSettings = RepeatSettings { delay: 0, interval: 0 };
+
+    /// Whether a key that has been held for `frames` frames repeats on this frame.
+    fn fires(&self, frames: u32) -> bool {
+        if frames == 0 {
+            false
+        } else if frames == 1 {
+            true
+        } else if self.interval == 0 || frames < self.delay + 1 {
+            false
+        } else {
+            (frames - 1 - self.delay) % self.interval == 0
+        }
+    }
+}
+
+/// Tracks the keypad from frame to frame, so that presses and releases can be told apart from keys
+/// that are simply held down. `update` has to be called exactly once per frame.
+pub struct Input {
+    current: KeySet,
+    previous: KeySet,
+    /// How many frames each key has been held for, indexed by `Key::index`
+    held_frames: [u32; 10],
+    /// How each key repeats, indexed by `Key::index`
+    repeat: [RepeatSettings; 10],
+}
+
+impl Input {
+    /// A tracker that assumes no keys were held before the first `update`.
+    pub fn new() -> Self {
+        Input {
+            current: KeySet::empty(),
+            previous: KeySet::empty(),
+            held_frames: [0; 10],
+            repeat: [RepeatSettings::DEFAULT; 10],
+        }
+    }
+
+    /// Reads the keypad and advances the tracker by one frame.
+    pub fn update(&mut self) {
+        self.update_with(InputState::current());
+    }
+
+    /// Advances the tracker by one frame, using `state` instead of reading the keypad.
+    pub fn update_with(&mut self, state: InputState) {
+        self.previous = self.current;
+        self.current = state.pressed_keys();
+        for key in Key::ALL.iter() {
+            let frames = &mut self.held_frames[key.index()];
+            if self.current.contains(*key) {
+                *frames = frames.saturating_add(1);
+            } else {
+                *frames = 0;
+            }
+        }
+    }
+
+    /// Whether `key` is down this frame.
+    pub fn held(&self, key: Key) -> bool {
+        self.current.contains(key)
+    }
+
+    /// Whether `key` is down this frame, but wasn't last frame.
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.current.contains(key) && !self.previous.contains(key)
+    }
+
+    /// Whether `key` was down last frame, but isn't this frame.
+    pub fn just_released(&self, key: Key) -> bool {
+        !self.current.contains(key) && self.previous.contains(key)
+    }
+
+    /// The number of consecutive frames `key` has been down for, including this one; 0 if it is up.
+    pub fn held_frames(&self, key: Key) -> u32 {
+        self.held_frames[key.index()]
+    }
+
+    /// All of the keys that are down this frame.
+    pub fn held_keys(&self) -> KeySet {
+        self.current
+    }
+
+    /// The keys that are down this frame, but weren't last frame.
+    pub fn pressed_keys(&self) -> KeySet {
+        self.current.difference(self.previous)
+    }
+
+    /// The keys that were down last frame, but aren't this frame.
+    pub fn released_keys(&self) -> KeySet {
+        self.previous.difference(self.current)
+    }
+
+    /// Sets how every key in `keys` repeats, e.g. a fast repeat for the D-pad and none for A and B.
+    pub fn set_repeat(&mut self, keys: KeySet, settings: RepeatSettings) {
+        for key in keys {
+            self.repeat[key.index()] = settings;
+        }
+    }
+
+    /// Whether `key` was just pressed, or has been held long enough to repeat on this frame. This is
+    /// what menu cursors should move on.
+    pub fn repeated(&self, key: Key) -> bool {
+        let i = key.index();
+        self.repeat[i].fires(self.held_frames[i])
+    }
+
+    /// All of the keys for which `repeated` is true this frame.
+    pub fn repeated_keys(&self) -> KeySet {
+        let mut keys = KeySet::empty();
+        for key in Key::ALL.iter() {
+            if self.repeated(*key) {
+                keys = keys.add(*key);
+            }
+        }
+        keys
+    }
+}
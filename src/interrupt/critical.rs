@@ -0,0 +1,72 @@
+use core::cell::UnsafeCell;
+use core::intrinsics::{ volatile_load, volatile_store };
+use core::marker::PhantomData;
+use core::sync::atomic::{ compiler_fence, Ordering };
+use reg;
+
+/// Proof that interrupts are disabled. It can only be created by `free`, and only lives for as
+/// long as the closure passed to it runs.
+pub struct CriticalSection<'cs> {
+    _lifetime: PhantomData<&'cs ()>,
+}
+
+/// Runs `f` with interrupts disabled (REG_IME cleared), restoring REG_IME to its previous value
+/// afterwards. Critical sections can be nested, and are safe to use inside interrupt handlers.
+pub fn free<F, R>(f: F) -> R where F: FnOnce(&CriticalSection) -> R {
+    unsafe {
+        let ime = reg::REG_IME.num as * mut u16;
+        let saved = volatile_load(ime);
+        volatile_store(ime, 0);
+        // Volatile accesses only order other volatile accesses; the fences keep the closure's plain
+        // loads and stores from being moved outside of the IME=0 window.
+        compiler_fence(Ordering::SeqCst);
+
+        let result = f(&CriticalSection { _lifetime: PhantomData });
+
+        compiler_fence(Ordering::SeqCst);
+        volatile_store(ime, saved);
+        result
+    }
+}
+
+/// A cell for sharing state between the main loop and interrupt handlers. The contents can only be
+/// borrowed inside a critical section, so an interrupt can't observe (or cause) a half finished
+/// update. Wrap the contents in a `Cell` or `RefCell` to mutate them:
+///
+/// ```ignore
+/// static FRAMES: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+///
+/// fn on_vblank() {
+///     interrupt::free(|cs| {
+///         let frames = FRAMES.borrow(cs);
+///         frames.set(frames.get() + 1);
+///     });
+/// }
+/// ```
+pub struct Mutex<T> {
+    inner: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Mutex { inner: UnsafeCell::new(value) }
+    }
+
+    /// Borrows the contents for as long as the critical section lasts.
+    pub fn borrow<'cs>(&'cs self, _cs: &'cs CriticalSection) -> &'cs T {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// Borrows the contents mutably. No critical section is needed, since the `&mut self` already
+    /// guarantees that nothing else can access the contents.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.get() }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+// The GBA has a single core, and the contents are only reachable with interrupts disabled.
+unsafe impl<T: Send> Sync for Mutex<T> {}
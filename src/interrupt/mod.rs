@@ -0,0 +1,337 @@
+//! The interrupt subsystem. A master interrupt service routine, written in ARM assembly and stored
+//! in IWRAM, acknowledges pending interrupts and hands them to a dispatcher that calls the handler
+//! registered for every source that fired.
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/interrupts.htm
+use core::intrinsics::{ volatile_load, volatile_store };
+use reg;
+
+mod critical;
+pub use self::critical::{ free, CriticalSection, Mutex };
+
+/// Where the BIOS looks for the address of the interrupt service routine.
+const ISR_ADDRESS: u32 = 0x03007FFC;
+
+global_asm!(r#"
+    .section .iwram.gbalib_isr, "ax", %progbits
+    .arm
+    .align 2
+
+    @ The BIOS has already saved r0-r3, r12 and lr on the IRQ stack before jumping here, and
+    @ expects the same registers to be intact when it gets control back.
+    .global __gbalib_isr
+    .type __gbalib_isr, %function
+__gbalib_isr:
+    mov     r2, #0x04000000
+    add     r3, r2, #0x200
+    @ r1 = IE in the low halfword, IF in the high halfword
+    ldr     r1, [r3]
+    and     r0, r1, r1, lsr #16
+    @ Acknowledge the interrupts in REG_IF...
+    strh    r0, [r3, #2]
+    @ ...and in the BIOS mirror at 0x03007FF8 (0x03FFFFF8 mirrors it), for IntrWait
+    ldrh    r1, [r2, #-8]
+    orr     r1, r1, r0
+    strh    r1, [r2, #-8]
+
+    @ A nested interrupt would overwrite SPSR and lr_irq, so they are saved along with REG_IME.
+    @ IME is cleared until the dispatcher decides which interrupts may preempt the handler.
+    mrs     r1, spsr
+    ldrh    r12, [r3, #8]
+    stmfd   sp!, {r1, r3, r12, lr}
+    mov     r1, #0
+    strh    r1, [r3, #8]
+
+    @ Switch to system mode with IRQs enabled at the CPU, so the handlers run on the (much
+    @ bigger) user stack and can be interrupted.
+    mov     r1, #0x1F
+    msr     cpsr_c, r1
+
+    @ Call the dispatcher (Thumb code) with r0 = the interrupts that fired
+    stmfd   sp!, {r4, lr}
+    ldr     r1, =__gbalib_irq_dispatch
+    mov     lr, pc
+    bx      r1
+    ldmfd   sp!, {r4, lr}
+
+    @ Back to IRQ mode with IRQs disabled, and restore everything that was saved
+    mov     r1, #0x92
+    msr     cpsr_c, r1
+    ldmfd   sp!, {r1, r3, r12, lr}
+    strh    r12, [r3, #8]
+    msr     spsr_cf, r1
+    bx      lr
+
+    .pool
+    .thumb
+"#);
+
+extern "C" {
+    fn __gbalib_isr();
+}
+
+/// The interrupt sources, as their bits in REG_IE and REG_IF.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Irq {
+    VBlank  = 0x0001,
+    HBlank  = 0x0002,
+    VCount  = 0x0004,
+    Timer0  = 0x0008,
+    Timer1  = 0x0010,
+    Timer2  = 0x0020,
+    Timer3  = 0x0040,
+    Serial  = 0x0080,
+    Dma0    = 0x0100,
+    Dma1    = 0x0200,
+    Dma2    = 0x0400,
+    Dma3    = 0x0800,
+    Keypad  = 0x1000,
+    GamePak = 0x2000,
+}
+
+/// The number of interrupt sources.
+pub const IRQ_COUNT: usize = 14;
+
+impl Irq {
+    pub const ALL: [Irq; IRQ_COUNT] = [
+        Irq::VBlank, Irq::HBlank, Irq::VCount, Irq::Timer0, Irq::Timer1, Irq::Timer2,
+        Irq::Timer3, Irq::Serial, Irq::Dma0, Irq::Dma1, Irq::Dma2, Irq::Dma3, Irq::Keypad,
+        Irq::GamePak,
+    ];
+
+    /// The position of this source's bit in REG_IE and REG_IF.
+    pub fn index(self) -> usize {
+        (self as u16).trailing_zeros() as usize
+    }
+
+    /// The register and bit that have to be set for the peripheral itself to raise this interrupt.
+    /// The game pak interrupt has no such bit.
+    fn peripheral_bit(self) -> Option<(u32, u16)> {
+        unsafe {
+            match self {
+                Irq::VBlank => Some((reg::REG_DISPSTAT.num, 0x0008)),
+                Irq::HBlank => Some((reg::REG_DISPSTAT.num, 0x0010)),
+                Irq::VCount => Some((reg::REG_DISPSTAT.num, 0x0020)),
+                Irq::Timer0 | Irq::Timer1 | Irq::Timer2 | Irq::Timer3 => {
+                    let n = (self.index() - Irq::Timer0.index()) as u32;
+                    Some((reg::REG_TIMER_BASE.num + n * 4 + 2, 0x0040))
+                },
+                Irq::Serial => Some((reg::REG_SIOCNT.num, 0x4000)),
+                Irq::Dma0 | Irq::Dma1 | Irq::Dma2 | Irq::Dma3 => {
+                    let n = (self.index() - Irq::Dma0.index()) as u32;
+                    Some((reg::REG_DMA_BASE.num + n * 12 + 10, 0x4000))
+                },
+                Irq::Keypad => Some((reg::REG_KEYCNT.num, 0x4000)),
+                Irq::GamePak => None,
+            }
+        }
+    }
+}
+
+/// An interrupt handler. Handlers run in system mode, on the user stack, with interrupts disabled
+/// unless a source with a higher priority has been configured (see `set_priority`).
+pub type Handler = fn();
+
+static mut HANDLERS: [Option<Handler>; IRQ_COUNT] = [None; IRQ_COUNT];
+
+/// The priority of every source; lower numbers are more important.
+static mut PRIORITIES: [u8; IRQ_COUNT] = [0; IRQ_COUNT];
+
+/// For every source, the sources that are allowed to interrupt its handler.
+static mut PREEMPT_MASKS: [u16; IRQ_COUNT] = [0; IRQ_COUNT];
+
+/// The sources that are enabled, as set by `enable` and `disable`. REG_IE only holds the ones that
+/// are allowed through `ACTIVE_MASK`.
+static mut ENABLED: u16 = 0;
+
+/// The sources that may interrupt the handlers that are running; all of them outside of handlers.
+static mut ACTIVE_MASK: u16 = 0xFFFF;
+
+/// Writes the enabled sources that are currently allowed through to REG_IE.
+#[inline(always)]
+unsafe fn write_ie() {
+    volatile_store(reg::REG_IE.num as * mut u16, ENABLED & ACTIVE_MASK);
+}
+
+/// The most interrupts that can be in progress at once, counting the outermost one. Every level
+/// takes 24 bytes of BIOS frame and 16 bytes of ISR frame on the 0xA0 byte IRQ stack, so this keeps
+/// nesting well clear of overflowing it; at the deepest level handlers run without preemption.
+pub const MAX_NESTING: u32 = 3;
+
+/// The number of interrupts in progress.
+static mut DEPTH: u32 = 0;
+
+/// Called by the master ISR with the interrupts that fired (and have already been acknowledged).
+/// REG_IME is cleared when this is called. The handlers run from the most to the least important,
+/// so an important source that fired at the same time as a less important one isn't kept waiting.
+#[no_mangle]
+pub extern "C" fn __gbalib_irq_dispatch(fired: u32) {
+    unsafe {
+        let ime = reg::REG_IME.num as * mut u16;
+        DEPTH += 1;
+        let mut pending = fired as u16 & ((1 << IRQ_COUNT) - 1);
+        while pending != 0 {
+            // The most important pending source; on a tie, the one with the lowest bit
+            let mut i = pending.trailing_zeros() as usize;
+            for j in i + 1..IRQ_COUNT {
+                if pending & (1 << j) != 0 && PRIORITIES[j] < PRIORITIES[i] {
+                    i = j;
+                }
+            }
+            pending &= !(1 << i);
+
+            if let Some(handler) = HANDLERS[i] {
+                let mask = PREEMPT_MASKS[i];
+                if mask == 0 || DEPTH >= MAX_NESTING {
+                    handler();
+                } else {
+                    // Only let the more important sources through while this handler runs. REG_IE
+                    // is rebuilt from ENABLED afterwards, so `enable` and `disable` calls made by
+                    // the handler (or by anything that interrupted it) are kept.
+                    let outer = ACTIVE_MASK;
+                    ACTIVE_MASK = outer & mask;
+                    write_ie();
+                    volatile_store(ime, 1);
+                    handler();
+                    volatile_store(ime, 0);
+                    ACTIVE_MASK = outer;
+                    write_ie();
+                }
+            }
+        }
+        DEPTH -= 1;
+    }
+}
+
+/// Installs the master ISR and enables interrupts (REG_IME). No source is enabled until `enable`
+/// is called for it.
+pub fn init() {
+    unsafe {
+        volatile_store(reg::REG_IME.num as * mut u16, 0);
+        ENABLED = volatile_load(reg::REG_IE.num as * const u16);
+        ACTIVE_MASK = 0xFFFF;
+        volatile_store(ISR_ADDRESS as * mut u32, __gbalib_isr as u32);
+        volatile_store(reg::REG_IME.num as * mut u16, 1);
+    }
+}
+
+/// Sets whether interrupts are serviced at all (REG_IME).
+pub fn set_master_enabled(enabled: bool) {
+    unsafe { volatile_store(reg::REG_IME.num as * mut u16, enabled as u16) }
+}
+
+/// Whether interrupts are serviced at all (REG_IME).
+pub fn master_enabled() -> bool {
+    unsafe { volatile_load(reg::REG_IME.num as * const u16) & 1 != 0 }
+}
+
+/// Registers the handler that is called whenever `irq` fires, replacing the current one. Passing
+/// `None` removes it; the interrupt is still acknowledged.
+pub fn set_handler(irq: Irq, handler: Option<Handler>) {
+    // The dispatcher must never see a half written entry
+    free(|_| unsafe { HANDLERS[irq.index()] = handler });
+}
+
+/// Enables `irq` in REG_IE, and sets the bit that makes the peripheral raise it: the DISPSTAT
+/// bits for VBlank / HBlank / VCount, or the IRQ bit of the timer, DMA channel, SIOCNT or KEYCNT.
+pub fn enable(irq: Irq) {
+    free(|_| unsafe {
+        if let Some((addr, bit)) = irq.peripheral_bit() {
+            let p = addr as * mut u16;
+            volatile_store(p, volatile_load(p) | bit);
+        }
+        ENABLED |= irq as u16;
+        write_ie();
+    })
+}
+
+/// Disables `irq` in REG_IE, and clears the bit that makes the peripheral raise it.
+pub fn disable(irq: Irq) {
+    free(|_| unsafe {
+        ENABLED &= !(irq as u16);
+        write_ie();
+        if let Some((addr, bit)) = irq.peripheral_bit() {
+            let p = addr as * mut u16;
+            volatile_store(p, volatile_load(p) & !bit);
+        }
+    })
+}
+
+/// Whether `irq` is enabled, even if it is held back from REG_IE while a handler runs.
+pub fn is_enabled(irq: Irq) -> bool {
+    unsafe { ENABLED & irq as u16 != 0 }
+}
+
+/// Runs `f` with only the `irqs` let through to REG_IE. The other sources stay enabled and are let
+/// through again once `f` returns, and `enable` and `disable` calls made meanwhile (by `f` or by a
+/// handler) are kept.
+pub fn with_only<F: FnOnce() -> R, R>(irqs: &[Irq], f: F) -> R {
+    let mask = irqs.iter().fold(0u16, |mask, &irq| mask | irq as u16);
+    let outer = free(|_| unsafe {
+        let outer = ACTIVE_MASK;
+        ACTIVE_MASK = outer & mask;
+        write_ie();
+        outer
+    });
+    let result = f();
+    free(|_| unsafe {
+        ACTIVE_MASK = outer;
+        write_ie();
+    });
+    result
+}
+
+/// The reasons a priority order can be rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PriorityError {
+    /// The order lists more than `IRQ_COUNT` sources.
+    TooLong,
+}
+
+/// Recomputes which sources may interrupt each handler from PRIORITIES.
+unsafe fn update_preempt_masks() {
+    for i in 0..IRQ_COUNT {
+        let mut mask = 0u16;
+        for j in 0..IRQ_COUNT {
+            if PRIORITIES[j] < PRIORITIES[i] {
+                mask |= 1 << j;
+            }
+        }
+        PREEMPT_MASKS[i] = mask;
+    }
+}
+
+/// Sets the priority of `irq`; lower numbers are more important. While a handler runs, any source
+/// with a strictly more important priority can interrupt it. Every source starts with priority 0,
+/// so by default handlers are never interrupted. At most `MAX_NESTING` handlers can be interrupting
+/// each other at once, so only that many priority levels are useful.
+pub fn set_priority(irq: Irq, priority: u8) {
+    free(|_| unsafe {
+        PRIORITIES[irq.index()] = priority;
+        update_preempt_masks();
+    })
+}
+
+pub fn priority(irq: Irq) -> u8 {
+    unsafe { PRIORITIES[irq.index()] }
+}
+
+/// Sets the priority of every source at once, from most to least important: the first source in
+/// `order` gets priority 0, the second priority 1, and so on. Sources that aren't listed are less
+/// important than all of the listed ones.
+pub fn set_priority_order(order: &[Irq]) -> Result<(), PriorityError> {
+    if order.len() > IRQ_COUNT {
+        return Err(PriorityError::TooLong);
+    }
+    free(|_| unsafe {
+        for p in PRIORITIES.iter_mut() {
+            *p = order.len() as u8;
+        }
+        for (i, irq) in order.iter().enumerate() {
+            PRIORITIES[irq.index()] = i as u8;
+        }
+        update_preempt_masks();
+    });
+    Ok(())
+}
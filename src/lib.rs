@@ -1,15 +1,15 @@
 #![no_std]
-#![feature(asm, lang_items, core_intrinsics, const_fn, untagged_unions, arbitrary_self_types, const_fn_union)]
+#![feature(asm, lang_items, core_intrinsics, const_fn, untagged_unions, arbitrary_self_types, const_fn_union, global_asm)]
 
 #![allow(dead_code)]
 
 pub extern crate gbaimg;
 pub use gbaimg::{ img_as_palleted_sprite_8bpp, img_as_palleted_sprite_4bpp };
+pub extern crate gbadecompress as decompress;
 
 mod lang;
 pub use lang::*;
 
-#[macro_use]
 pub mod interrupt;
 pub mod reg;
 pub mod ptr;
@@ -18,5 +18,15 @@ pub mod alloc;
 pub mod boxed;
 pub mod collections;
 pub mod mem;
+pub mod bios;
 pub mod graphics;
+pub mod dma;
+pub mod timer;
+pub mod sound;
+pub mod serial;
+pub mod peripherals;
+pub mod debug;
+#[macro_use]
+pub mod profile;
+pub use peripherals::Peripherals;
 
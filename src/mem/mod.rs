@@ -1,98 +1,306 @@
+//! Memory routines that are safe to use on every kind of memory the GBA has. VRAM, palette RAM and
+//! OAM don't support 8-bit writes (they either ignore them or write the byte to both halves of the
+//! halfword), so lone bytes at the start or end of a region in those are merged into the halfword
+//! that contains them. Everywhere else bytes are stored as they are, so nothing outside the region
+//! is ever written. Reads are always safe, so they are done at whatever width is convenient.
+//!
+//! All accesses are volatile, which also stops the compiler from recognizing the loops below as a
+//! memcpy / memset and replacing them with a call to themselves.
 use ptr::Ptr;
 use core::mem::{ size_of };
+use core::intrinsics::{ volatile_load, volatile_store };
 
-// const debug: Ptr<u32> = Ptr::from_u32(0x03000000);
-pub unsafe fn memcpy<T: Sized>(dst: Ptr<T>, src: Ptr<T>, items: u32) -> Ptr<T> where Ptr<T>: Clone + Copy {
-    // *debug = 0;
-    let mut p = Ptr::<u32>::from_u32(0x03000004);
-    if items == 0 || dst.is_null() || src.is_null() {
-        *p = 0xBEEF;
-        return dst;
+mod block;
+pub use self::block::{ copy32, fill32 };
+
+/// Copies `items` T's from src to dst. The two regions must not overlap; use `memmove` if they
+/// might.
+pub unsafe fn memcpy<T: Sized>(dst: Ptr<T>, src: Ptr<T>, items: u32) -> Ptr<T> {
+    copy_forward(dst.num, src.num, items * size_of::<T>() as u32);
+    dst
+}
+
+/// Copies `items` T's from src to dst. The two regions may overlap.
+pub unsafe fn memmove<T: Sized>(dst: Ptr<T>, src: Ptr<T>, items: u32) -> Ptr<T> {
+    move_bytes(dst.num, src.num, items * size_of::<T>() as u32);
+    dst
+}
+
+/// Sets every byte of the `items` T's starting at dst to `val`.
+pub unsafe fn memset<T: Sized>(dst: Ptr<T>, val: u8, items: u32) -> Ptr<T> {
+    fill(dst.num, val, items * size_of::<T>() as u32);
+    dst
+}
+
+/// Compares the `items` T's at a and b byte by byte. The result is negative, zero or positive if
+/// the first differing byte of a is less than, equal to or greater than the one of b.
+pub unsafe fn memcmp<T: Sized>(a: Ptr<T>, b: Ptr<T>, items: u32) -> i32 {
+    compare(a.num, b.num, items * size_of::<T>() as u32)
+}
+
+#[inline(always)]
+unsafe fn read8(addr: u32) -> u8 { volatile_load(addr as * const u8) }
+
+#[inline(always)]
+unsafe fn read16(addr: u32) -> u16 { volatile_load(addr as * const u16) }
+
+/// Reads two bytes that may not be halfword aligned, in little endian order.
+#[inline(always)]
+unsafe fn read16_unaligned(addr: u32) -> u16 {
+    if addr & 1 == 0 {
+        read16(addr)
+    } else {
+        read8(addr) as u16 | ((read8(addr + 1) as u16) << 8)
     }
+}
 
-    //*debug = items * size_of::<T>() as u32;
+#[inline(always)]
+unsafe fn write16(addr: u32, val: u16) { volatile_store(addr as * mut u16, val) }
 
-    let mut count;
-    let mut dst16;
-    let mut src8 = src.transmute::<u8>();
+#[inline(always)]
+unsafe fn write32(addr: u32, val: u32) { volatile_store(addr as * mut u32, val) }
 
-    let mut len = items * (size_of::<T>() as u32);
+/// Whether `addr` is in palette RAM, VRAM or OAM, which ignore 8-bit writes.
+#[inline(always)]
+fn no_byte_writes(addr: u32) -> bool {
+    addr >= 0x05000000 && addr < 0x08000000
+}
 
-    // Copy data 4 words at a time - except for the tail, which is 0, 1, 2, or 3 words
-    if (src.num | dst.num) & 4 == 0 && len >= 4 {
-        let mut src32 = src.transmute::<u32>();
-        let mut dst32 = dst.transmute::<u32>();
+/// Writes a single byte. In palette RAM, VRAM and OAM this reads the halfword that contains it and
+/// writes it back with the byte replaced; that rewrites the other byte too, so it would lose an
+/// update made to it in between (by an interrupt, say) and isn't done anywhere else.
+#[inline(always)]
+pub(crate) unsafe fn write8(addr: u32, val: u8) {
+    if !no_byte_writes(addr) {
+        volatile_store(addr as * mut u8, val);
+        return;
+    }
+    let half = addr & !1;
+    let old = read16(half);
+    if addr & 1 == 0 {
+        write16(half, (old & 0xFF00) | val as u16);
+    } else {
+        write16(half, (old & 0x00FF) | ((val as u16) << 8));
+    }
+}
 
-        count = len / 4;
-        let mut tail_words = count & 3;
-        count /= 4;
+pub(crate) unsafe fn copy_forward(mut dst: u32, mut src: u32, mut len: u32) {
+    // If src and dst have the same word alignment, copy up to the first word boundary and then copy
+    // words for as long as possible.
+    if (src ^ dst) & 3 == 0 {
+        while dst & 3 != 0 && len != 0 {
+            write8(dst, read8(src));
+            dst += 1;
+            src += 1;
+            len -= 1;
+        }
 
-        while tail_words != 0 {
-            *dst32 = *src32;
-            dst32 = dst32.offset(1);
-            src32 = src32.offset(1);
-            tail_words-= 1;
+        while len >= 4 {
+            write32(dst, volatile_load(src as * const u32));
+            dst += 4;
+            src += 4;
+            len -= 4;
         }
+    }
 
-        while count != 0 {
-            *dst32 = *src32;
-            dst32 = dst32.offset(1);
-            src32 = src32.offset(1);
+    if dst & 1 != 0 && len != 0 {
+        write8(dst, read8(src));
+        dst += 1;
+        src += 1;
+        len -= 1;
+    }
 
-            *dst32 = *src32;
-            dst32 = dst32.offset(1);
-            src32 = src32.offset(1);
+    // dst is now halfword aligned; src may not be
+    while len >= 2 {
+        write16(dst, read16_unaligned(src));
+        dst += 2;
+        src += 2;
+        len -= 2;
+    }
 
-            *dst32 = *src32;
-            dst32 = dst32.offset(1);
-            src32 = src32.offset(1);
+    if len != 0 {
+        write8(dst, read8(src));
+    }
+}
 
-            *dst32 = *src32;
-            dst32 = dst32.offset(1);
-            src32 = src32.offset(1);
+pub(crate) unsafe fn copy_backward(dst: u32, src: u32, mut len: u32) {
+    // Work from the end of both regions towards the start, mirroring copy_forward
+    let mut dst = dst + len;
+    let mut src = src + len;
 
-            count -= 1;
+    if (src ^ dst) & 3 == 0 {
+        while dst & 3 != 0 && len != 0 {
+            dst -= 1;
+            src -= 1;
+            len -= 1;
+            write8(dst, read8(src));
         }
 
-        len &= 3;
-        if len == 0 {
-            *p = 0xDEAD;
-            return dst;
+        while len >= 4 {
+            dst -= 4;
+            src -= 4;
+            len -= 4;
+            write32(dst, volatile_load(src as * const u32));
         }
+    }
+
+    if dst & 1 != 0 && len != 0 {
+        dst -= 1;
+        src -= 1;
+        len -= 1;
+        write8(dst, read8(src));
+    }
+
+    while len >= 2 {
+        dst -= 2;
+        src -= 2;
+        len -= 2;
+        write16(dst, read16_unaligned(src));
+    }
+
+    if len != 0 {
+        write8(dst - 1, read8(src - 1));
+    }
+}
 
-        src8 = src32.transmute();
-        dst16 = dst32.transmute();
+pub(crate) unsafe fn move_bytes(dst: u32, src: u32, len: u32) {
+    // Copying forward is fine as long as dst doesn't start inside of src.
+    if dst <= src || dst >= src + len {
+        copy_forward(dst, src, len);
     } else {
-        let dst_offset = dst.num & 1;
-        dst16 = Ptr::<u16>::from_u32(dst.num - dst_offset);
+        copy_backward(dst, src, len);
+    }
+}
 
-        if dst_offset != 0 {
-            *dst16 = (*dst16 & 0xFF) | ((*src8 as u16) << 8);
-            src8 = src8.offset(1);
-            dst16 = dst16.offset(1);
-            len -= 1;
-            if len == 0 {
-                *p = 0xCAFE;
-                return dst;
-            }
-        }
+pub(crate) unsafe fn fill(mut dst: u32, val: u8, mut len: u32) {
+    let val16 = val as u16 | ((val as u16) << 8);
+    let val32 = val16 as u32 | ((val16 as u32) << 16);
+
+    if dst & 1 != 0 && len != 0 {
+        write8(dst, val);
+        dst += 1;
+        len -= 1;
+    }
+
+    if dst & 2 != 0 && len >= 2 {
+        write16(dst, val16);
+        dst += 2;
+        len -= 2;
+    }
+
+    while len >= 4 {
+        write32(dst, val32);
+        dst += 4;
+        len -= 4;
+    }
+
+    if len >= 2 {
+        write16(dst, val16);
+        dst += 2;
+        len -= 2;
     }
 
-    count = len / 2;
+    if len != 0 {
+        write8(dst, val);
+    }
+}
 
-    while count != 0 {
-        *dst16 = src8[0] as u16 | ((src8[1] as u16) << 8);
-        dst16 = dst16.offset(1);
-        src8 = src8.offset(2);
+pub(crate) unsafe fn compare(mut a: u32, mut b: u32, mut len: u32) -> i32 {
+    while len != 0 {
+        let (x, y) = (read8(a), read8(b));
+        if x != y {
+            return x as i32 - y as i32;
+        }
+        a += 1;
+        b += 1;
         len -= 1;
-        count -= 1;
+    }
+    0
+}
+
+/// The C symbols rustc and LLVM emit calls to, including the ARM EABI variants.
+mod exports {
+    #[no_mangle]
+    pub unsafe extern "C" fn memcpy(dst: * mut u8, src: * const u8, n: usize) -> * mut u8 {
+        super::copy_forward(dst as u32, src as u32, n as u32);
+        dst
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn memmove(dst: * mut u8, src: * const u8, n: usize) -> * mut u8 {
+        super::move_bytes(dst as u32, src as u32, n as u32);
+        dst
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn memset(dst: * mut u8, c: i32, n: usize) -> * mut u8 {
+        super::fill(dst as u32, c as u8, n as u32);
+        dst
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn memcmp(a: * const u8, b: * const u8, n: usize) -> i32 {
+        super::compare(a as u32, b as u32, n as u32)
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memcpy(dst: * mut u8, src: * const u8, n: usize) {
+        super::copy_forward(dst as u32, src as u32, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memcpy4(dst: * mut u8, src: * const u8, n: usize) {
+        super::copy_forward(dst as u32, src as u32, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memcpy8(dst: * mut u8, src: * const u8, n: usize) {
+        super::copy_forward(dst as u32, src as u32, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memmove(dst: * mut u8, src: * const u8, n: usize) {
+        super::move_bytes(dst as u32, src as u32, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memmove4(dst: * mut u8, src: * const u8, n: usize) {
+        super::move_bytes(dst as u32, src as u32, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memmove8(dst: * mut u8, src: * const u8, n: usize) {
+        super::move_bytes(dst as u32, src as u32, n as u32);
+    }
+
+    /// Note that the EABI variant takes the length before the value.
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memset(dst: * mut u8, n: usize, c: i32) {
+        super::fill(dst as u32, c as u8, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memset4(dst: * mut u8, n: usize, c: i32) {
+        super::fill(dst as u32, c as u8, n as u32);
+    }
 
-    if len & 1 != 0 {
-        *dst16 = (*dst16 & !0xFF) | *src8 as u16;
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memset8(dst: * mut u8, n: usize, c: i32) {
+        super::fill(dst as u32, c as u8, n as u32);
     }
 
-    *p = 0xBABE;
-    return dst
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memclr(dst: * mut u8, n: usize) {
+        super::fill(dst as u32, 0, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memclr4(dst: * mut u8, n: usize) {
+        super::fill(dst as u32, 0, n as u32);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __aeabi_memclr8(dst: * mut u8, n: usize) {
+        super::fill(dst as u32, 0, n as u32);
+    }
 }
@@ -0,0 +1,71 @@
+//! Singleton ownership of the GBA's hardware. `Peripherals::take` hands out exactly one handle
+//! for every background, DMA channel, timer, OAM, palette RAM, the sound unit and the serial port,
+//! so that the borrow checker can stop two unrelated parts of a program from fighting over the
+//! same registers.
+
+use interrupt;
+use graphics::Palettes;
+use graphics::sprites::Oam;
+use graphics::tiled_bg::Background;
+use dma::{ Dma0, Dma1, Dma2, Dma3 };
+use timer::{ Timer0, Timer1, Timer2, Timer3 };
+use sound::Sound;
+use serial::Serial;
+
+static mut TAKEN: bool = false;
+
+pub struct Peripherals {
+    pub bg0: Background,
+    pub bg1: Background,
+    pub bg2: Background,
+    pub bg3: Background,
+    pub dma0: Dma0,
+    pub dma1: Dma1,
+    pub dma2: Dma2,
+    pub dma3: Dma3,
+    pub timer0: Timer0,
+    pub timer1: Timer1,
+    pub timer2: Timer2,
+    pub timer3: Timer3,
+    pub oam: Oam,
+    pub palettes: Palettes,
+    pub sound: Sound,
+    pub serial: Serial,
+}
+
+impl Peripherals {
+
+    /// Returns all of the peripherals the first time it is called, and `None` every time after
+    /// that.
+    pub fn take() -> Option<Peripherals> {
+        // Interrupts are masked so that a handler can't take the peripherals between the check and
+        // the update of TAKEN.
+        interrupt::free(|_| unsafe {
+            if TAKEN { None } else { Some(Peripherals::steal()) }
+        })
+    }
+
+    /// Returns all of the peripherals, even if they have already been taken. Any handles that
+    /// already exist will alias the returned ones.
+    pub unsafe fn steal() -> Peripherals {
+        TAKEN = true;
+        Peripherals {
+            bg0: Background::new(0),
+            bg1: Background::new(1),
+            bg2: Background::new(2),
+            bg3: Background::new(3),
+            dma0: Dma0::new(),
+            dma1: Dma1::new(),
+            dma2: Dma2::new(),
+            dma3: Dma3::new(),
+            timer0: Timer0::new(),
+            timer1: Timer1::new(),
+            timer2: Timer2::new(),
+            timer3: Timer3::new(),
+            oam: Oam::new(),
+            palettes: Palettes::new(),
+            sound: Sound::new(),
+            serial: Serial::new(),
+        }
+    }
+}
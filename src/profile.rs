@@ -0,0 +1,188 @@
+//! Cycle accurate profiling. A `Stopwatch` chains two hardware timers together into a 32-bit
+//! cycle counter, and the `profile!` macro uses one to collect min / avg / max cycle counts for
+//! named sections of code into a fixed size table.
+//!
+//! A frame is 280,896 cycles long, so a section that takes longer than that is already a problem.
+use core::fmt;
+use core::intrinsics::volatile_load;
+use interrupt;
+use reg;
+use timer::{ Timer, Timer0, Timer1, Timer2, Timer3, TimerControl, Prescaler };
+
+/// Implemented by timers that count up when `Prev` overflows when they are in cascade mode.
+pub trait CascadesFrom<Prev: Timer>: Timer {}
+
+impl CascadesFrom<Timer0> for Timer1 {}
+impl CascadesFrom<Timer1> for Timer2 {}
+impl CascadesFrom<Timer2> for Timer3 {}
+
+/// Reads the 32-bit cycle count of two cascaded timers, starting with timer `low`. The high half
+/// is read twice, so that a carry between the two reads can't produce a torn value.
+#[inline(always)]
+unsafe fn read_cycles(low: u32) -> u32 {
+    let lo_ptr = (reg::REG_TIMER_BASE.num + low * 4) as * const u16;
+    let hi_ptr = (reg::REG_TIMER_BASE.num + (low + 1) * 4) as * const u16;
+    let hi = volatile_load(hi_ptr);
+    let mut lo = volatile_load(lo_ptr);
+    let hi2 = volatile_load(hi_ptr);
+    if hi != hi2 {
+        lo = volatile_load(lo_ptr);
+    }
+    ((hi2 as u32) << 16) | lo as u32
+}
+
+/// A 32-bit cycle counter made of two cascaded timers: `low` counts every cycle and `high`
+/// counts the overflows of `low`. It wraps after about 256 seconds.
+pub struct Stopwatch<L: Timer, H: CascadesFrom<L>> {
+    low: L,
+    high: H,
+}
+
+impl<L: Timer, H: CascadesFrom<L>> Stopwatch<L, H> {
+    pub fn new(low: L, high: H) -> Self {
+        Stopwatch { low, high }
+    }
+
+    /// Resets the count to zero and starts counting.
+    pub fn start(&mut self) {
+        self.low.stop();
+        self.high.start(0, *TimerControl::new().set_cascade(true));
+        self.low.start(0, *TimerControl::new().set_prescaler(Prescaler::_1));
+    }
+
+    /// Stops counting; `elapsed` keeps returning the count at the time it was stopped.
+    pub fn stop(&mut self) {
+        self.low.stop();
+        self.high.stop();
+    }
+
+    /// The number of cycles since `start` was called.
+    pub fn elapsed(&self) -> u32 {
+        unsafe { read_cycles(L::INDEX) }
+    }
+
+    /// Stops the stopwatch and gives the timers back.
+    pub fn release(mut self) -> (L, H) {
+        self.stop();
+        (self.low, self.high)
+    }
+}
+
+/// The maximum number of sections the profiler can keep track of.
+pub const MAX_SECTIONS: usize = 32;
+
+/// The statistics collected for one named section.
+#[derive(Copy, Clone)]
+pub struct Section {
+    pub name: &'static str,
+    pub count: u32,
+    pub min: u32,
+    pub max: u32,
+    /// The sum of all samples; when it would overflow, it is halved along with `count` so the
+    /// average stays correct.
+    total: u32,
+}
+
+impl Section {
+    const EMPTY: Section = Section { name: "", count: 0, min: 0, max: 0, total: 0 };
+
+    /// The average number of cycles the section took.
+    pub fn avg(&self) -> u32 {
+        if self.count == 0 { 0 } else { self.total / self.count }
+    }
+
+    fn add(&mut self, cycles: u32) {
+        if self.count == 0 {
+            self.min = cycles;
+            self.max = cycles;
+        } else {
+            if cycles < self.min { self.min = cycles; }
+            if cycles > self.max { self.max = cycles; }
+        }
+        while self.total.checked_add(cycles).is_none() || self.count == u32::max_value() {
+            self.total >>= 1;
+            self.count >>= 1;
+        }
+        self.total += cycles;
+        self.count += 1;
+    }
+}
+
+/// The low timer of the stopwatch installed with `install`, if there is one.
+static mut CLOCK: Option<u32> = None;
+static mut SECTIONS: [Section; MAX_SECTIONS] = [Section::EMPTY; MAX_SECTIONS];
+static mut SECTION_COUNT: usize = 0;
+
+/// Starts `stopwatch` and uses it as the clock for `profile!` for the rest of the program. The
+/// timers stay running and can't be taken back.
+pub fn install<L: Timer, H: CascadesFrom<L>>(mut stopwatch: Stopwatch<L, H>) {
+    stopwatch.start();
+    unsafe { CLOCK = Some(L::INDEX) }
+}
+
+/// The current value of the installed stopwatch, or 0 if none is installed.
+#[inline(always)]
+pub fn now() -> u32 {
+    unsafe {
+        match CLOCK {
+            Some(low) => read_cycles(low),
+            None => 0,
+        }
+    }
+}
+
+/// Adds a sample to the named section, creating it if it doesn't exist yet. Samples for new
+/// sections are dropped once the table is full. Safe to call from interrupt handlers.
+pub fn record(name: &'static str, cycles: u32) {
+    // A handler that records a section must not interrupt a half done update of the table
+    interrupt::free(|_| unsafe {
+        for section in SECTIONS[..SECTION_COUNT].iter_mut() {
+            if section.name == name {
+                section.add(cycles);
+                return;
+            }
+        }
+        if SECTION_COUNT < MAX_SECTIONS {
+            SECTIONS[SECTION_COUNT] = Section { name, ..Section::EMPTY };
+            SECTIONS[SECTION_COUNT].add(cycles);
+            SECTION_COUNT += 1;
+        }
+    })
+}
+
+/// The statistics of every section recorded so far, in the order they were first recorded. If
+/// handlers record sections, the entries can change while the slice is being read.
+pub fn sections() -> &'static [Section] {
+    unsafe { &SECTIONS[..SECTION_COUNT] }
+}
+
+/// Forgets every section.
+pub fn reset() {
+    interrupt::free(|_| unsafe { SECTION_COUNT = 0 })
+}
+
+/// Writes one line per section to `w`, e.g. a `debug::MgbaLog` or a text buffer that is drawn as
+/// an on-screen overlay.
+pub fn write_report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    for section in sections() {
+        writeln!(w, "{}: min {} avg {} max {} ({}x)",
+                 section.name, section.min, section.avg(), section.max, section.count)?;
+    }
+    Ok(())
+}
+
+/// Times a block of code with the installed stopwatch, adding the cycle count to the named
+/// section, and evaluates to the value of the block.
+///
+/// ```ignore
+/// let hits = profile!("collision", { world.collide() });
+/// ```
+#[macro_export]
+macro_rules! profile {
+    ($name:expr, $body:block) => {{
+        let __profile_start = $crate::profile::now();
+        let __profile_result = $body;
+        $crate::profile::record($name, $crate::profile::now().wrapping_sub(__profile_start));
+        __profile_result
+    }}
+}
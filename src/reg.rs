@@ -1,11 +1,22 @@
 use ptr::Ptr;
 
 pub const REG_GRAPHICS_MODE: Ptr<u16> = unsafe { Ptr::from_u32(0x04000000) };
-pub const REG_BG_AFFINE: Ptr<u16> =     unsafe { Ptr::from_u32(0x04000000) };
+pub const REG_DISPSTAT: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000004) };
 pub const REG_VCOUNT: Ptr<u16> =        unsafe { Ptr::from_u32(0x04000006) };
 pub const REG_BGCNT: Ptr<u16> =         unsafe { Ptr::from_u32(0x04000008) };
 pub const REG_BG_OFS: Ptr<u16> =        unsafe { Ptr::from_u32(0x04000010) };
 pub const REG_BG_VOFS: Ptr<u16> =       unsafe { Ptr::from_u32(0x04000012) };
+pub const REG_BG_AFFINE: Ptr<u16> =     unsafe { Ptr::from_u32(0x04000020) };
+pub const REG_WINDOW_H: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000040) };
+pub const REG_WINDOW_V: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000044) };
+pub const REG_SOUNDCNT_L: Ptr<u16> =    unsafe { Ptr::from_u32(0x04000080) };
+pub const REG_SOUNDCNT_H: Ptr<u16> =    unsafe { Ptr::from_u32(0x04000082) };
+pub const REG_SOUNDCNT_X: Ptr<u16> =    unsafe { Ptr::from_u32(0x04000084) };
+pub const REG_SOUNDBIAS: Ptr<u16> =     unsafe { Ptr::from_u32(0x04000088) };
+pub const REG_FIFO_A: Ptr<u32> =        unsafe { Ptr::from_u32(0x040000A0) };
+pub const REG_FIFO_B: Ptr<u32> =        unsafe { Ptr::from_u32(0x040000A4) };
+pub const REG_DMA_BASE: Ptr<u32> =      unsafe { Ptr::from_u32(0x040000B0) };
+pub const REG_TIMER_BASE: Ptr<u16> =    unsafe { Ptr::from_u32(0x04000100) };
 pub const REG_DATA_IN0: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000120) };
 pub const REG_DATA_IN1: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000122) };
 pub const REG_DATA_IN2: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000124) };
@@ -13,8 +24,10 @@ pub const REG_DATA_IN3: Ptr<u16> =      unsafe { Ptr::from_u32(0x04000126) };
 pub const REG_SIOCNT: Ptr<u16> =        unsafe { Ptr::from_u32(0x04000128) };
 pub const REG_DATA_OUT: Ptr<u16> =      unsafe { Ptr::from_u32(0x0400012A) };
 pub const REG_KEY_INPUT: Ptr<u16> =     unsafe { Ptr::from_u32(0x04000130) };
+pub const REG_KEYCNT: Ptr<u16> =        unsafe { Ptr::from_u32(0x04000132) };
 pub const REG_RCNT: Ptr<u16> =          unsafe { Ptr::from_u32(0x04000134) };
 pub const REG_IE: Ptr<u16> =            unsafe { Ptr::from_u32(0x04000200) };
+pub const REG_IF: Ptr<u16> =            unsafe { Ptr::from_u32(0x04000202) };
 pub const REG_IME: Ptr<u16> =           unsafe { Ptr::from_u32(0x04000208) };
 
 pub const VRAM: Ptr<u16> =              unsafe { Ptr::from_u32(0x06000000) };
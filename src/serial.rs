@@ -0,0 +1,42 @@
+//! Access to the serial port (the link cable). Every communication mode is driven by SIOCNT and
+//! RCNT, so the whole port is owned as one `Serial` handle.
+
+use reg;
+
+/// Exclusive handle to the serial port registers (SIOCNT, RCNT and the SIO data registers).
+pub struct Serial { _private: () }
+
+impl Serial {
+    pub(crate) const unsafe fn new() -> Self { Serial { _private: () } }
+
+    pub fn control(&self) -> u16 {
+        unsafe { reg::REG_SIOCNT.volatile_load() }
+    }
+
+    pub fn set_control(&mut self, val: u16) -> &mut Self {
+        unsafe { reg::REG_SIOCNT.volatile_store(val) }
+        self
+    }
+
+    pub fn mode(&self) -> u16 {
+        unsafe { reg::REG_RCNT.volatile_load() }
+    }
+
+    pub fn set_mode(&mut self, val: u16) -> &mut Self {
+        unsafe { reg::REG_RCNT.volatile_store(val) }
+        self
+    }
+
+    /// Reads the data received from the n'th player in multiplayer mode. There are only 4
+    /// players, so n = n (mod 4)
+    pub fn multiplayer_data(&self, mut n: u32) -> u16 {
+        n &= 3;
+        unsafe { reg::REG_DATA_IN0.offset(n as i32).volatile_load() }
+    }
+
+    /// Sets the data that will be sent during the next multiplayer or 8-bit normal transfer.
+    pub fn set_data_out(&mut self, val: u16) -> &mut Self {
+        unsafe { reg::REG_DATA_OUT.volatile_store(val) }
+        self
+    }
+}
@@ -0,0 +1,45 @@
+//! Access to the GBA's sound unit. The four legacy PSG channels and the two Direct Sound FIFOs all
+//! share the SOUNDCNT registers, so they are owned as a single `Sound` handle.
+
+use reg;
+
+/// Exclusive handle to the sound unit (SOUNDCNT_L/H/X, SOUNDBIAS and the two FIFOs).
+pub struct Sound { _private: () }
+
+impl Sound {
+    /// Bit 7 of SOUNDCNT_X; when it is cleared all sound circuits are powered off.
+    const MASTER_ENABLE_MASK: u16 = 0x0080;
+
+    pub(crate) const unsafe fn new() -> Self { Sound { _private: () } }
+
+    /// Powers the sound circuits on or off. Most sound registers can't be written to while the
+    /// master enable bit is cleared.
+    pub fn set_master_enabled(&mut self, enabled: bool) -> &mut Self {
+        unsafe {
+            let x = reg::REG_SOUNDCNT_X.volatile_load() & !Self::MASTER_ENABLE_MASK;
+            reg::REG_SOUNDCNT_X.volatile_store(x | ((enabled as u16) << 7));
+        }
+        self
+    }
+
+    pub fn master_enabled(&self) -> bool {
+        unsafe { reg::REG_SOUNDCNT_X.volatile_load() & Self::MASTER_ENABLE_MASK != 0 }
+    }
+
+    /// Sets SOUNDCNT_L, which controls the volume and left / right output of the PSG channels.
+    pub fn set_psg_control(&mut self, val: u16) -> &mut Self {
+        unsafe { reg::REG_SOUNDCNT_L.volatile_store(val) }
+        self
+    }
+
+    /// Sets SOUNDCNT_H, which controls Direct Sound volume, output, timer selection and FIFO
+    /// resets.
+    pub fn set_direct_sound_control(&mut self, val: u16) -> &mut Self {
+        unsafe { reg::REG_SOUNDCNT_H.volatile_store(val) }
+        self
+    }
+
+    pub fn direct_sound_control(&self) -> u16 {
+        unsafe { reg::REG_SOUNDCNT_H.volatile_load() }
+    }
+}
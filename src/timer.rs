@@ -0,0 +1,192 @@
+//! The GBA has four hardware timers. Like the DMA channels, each one gets its own type so that
+//! a timer can only be driven by whoever owns it (see `Peripherals::take`).
+//!
+//! Further documentation can be found here: https://www.coranac.com/tonc/text/timers.htm
+use core::intrinsics::{ volatile_load, volatile_store };
+use reg;
+
+/// The frequency of the system clock that the timers count, in Hz.
+pub const CLOCK_HZ: u32 = 16_777_216;
+
+/// Exclusive handle to timer 0.
+pub struct Timer0 { _private: () }
+
+/// Exclusive handle to timer 1.
+pub struct Timer1 { _private: () }
+
+/// Exclusive handle to timer 2.
+pub struct Timer2 { _private: () }
+
+/// Exclusive handle to timer 3.
+pub struct Timer3 { _private: () }
+
+impl Timer0 { pub(crate) const unsafe fn new() -> Self { Timer0 { _private: () } } }
+impl Timer1 { pub(crate) const unsafe fn new() -> Self { Timer1 { _private: () } } }
+impl Timer2 { pub(crate) const unsafe fn new() -> Self { Timer2 { _private: () } } }
+impl Timer3 { pub(crate) const unsafe fn new() -> Self { Timer3 { _private: () } } }
+
+/// How many clock cycles it takes for a timer to count up once.
+#[derive(Copy, Clone)]
+#[repr(u16)]
+pub enum Prescaler {
+    /// 16.78 MHz, ~59.6 ns per tick
+    _1      = 0b00,
+    /// 262.21 kHz, ~3.815 us per tick
+    _64     = 0b01,
+    /// 65.536 kHz, ~15.26 us per tick
+    _256    = 0b10,
+    /// 16.384 kHz, ~61.04 us per tick
+    _1024   = 0b11,
+}
+
+impl Prescaler {
+    /// The number of clock cycles per tick.
+    pub fn cycles(self) -> u32 {
+        match self {
+            Prescaler::_1 => 1,
+            Prescaler::_64 => 64,
+            Prescaler::_256 => 256,
+            Prescaler::_1024 => 1024,
+        }
+    }
+}
+
+/// Represents a timer control register (TMxCNT_H) as per https://www.coranac.com/tonc/text/timers.htm
+#[derive(Copy, Clone)]
+pub struct TimerControl(u16);
+
+impl TimerControl {
+    const PRESCALER_MASK: u16 = 0x0003;
+    const CASCADE_MASK: u16 =   0x0004;
+    const IRQ_MASK: u16 =       0x0040;
+    const ENABLE_MASK: u16 =    0x0080;
+
+    /// A timer counting every clock cycle, with cascade and the overflow interrupt disabled.
+    pub const fn new() -> Self { TimerControl(0) }
+
+    pub fn set_prescaler(&mut self, prescaler: Prescaler) -> &mut Self {
+        self.0 &= !Self::PRESCALER_MASK;
+        self.0 |= prescaler as u16;
+        self
+    }
+
+    /// If enabled, the timer ignores its prescaler and counts up once every time the previous
+    /// timer overflows. Timer 0 has no previous timer, so this does nothing for it.
+    pub fn set_cascade(&mut self, cascade: bool) -> &mut Self {
+        self.0 &= !Self::CASCADE_MASK;
+        self.0 |= (cascade as u16) << 2;
+        self
+    }
+
+    /// If enabled, the timer raises its interrupt every time it overflows.
+    pub fn set_irq_enabled(&mut self, irq: bool) -> &mut Self {
+        self.0 &= !Self::IRQ_MASK;
+        self.0 |= (irq as u16) << 6;
+        self
+    }
+
+    pub fn bits(&self) -> u16 { self.0 }
+}
+
+mod sealed {
+    pub trait Timer {}
+    impl Timer for super::Timer0 {}
+    impl Timer for super::Timer1 {}
+    impl Timer for super::Timer2 {}
+    impl Timer for super::Timer3 {}
+
+    pub trait SoundTimer {}
+    impl SoundTimer for super::Timer0 {}
+    impl SoundTimer for super::Timer1 {}
+}
+
+/// Functionality shared by all four timers. Only implemented by the timer handles.
+pub trait Timer: sealed::Timer {
+    /// Which timer this is, [0, 3].
+    const INDEX: u32;
+
+    #[inline(always)]
+    fn counter_ptr() -> * mut u16 { unsafe { (reg::REG_TIMER_BASE.num + Self::INDEX * 4) as * mut u16 } }
+
+    #[inline(always)]
+    fn control_ptr() -> * mut u16 { unsafe { (reg::REG_TIMER_BASE.num + Self::INDEX * 4 + 2) as * mut u16 } }
+
+    /// Sets the value the counter is loaded with when the timer starts and every time it
+    /// overflows. A running timer only picks the new value up on its next overflow.
+    fn set_reload(&mut self, reload: u16) {
+        unsafe { volatile_store(Self::counter_ptr(), reload) }
+    }
+
+    /// Starts the timer, loading the counter with `reload`. A timer that is already running is
+    /// restarted.
+    fn start(&mut self, reload: u16, control: TimerControl) {
+        unsafe {
+            volatile_store(Self::control_ptr(), 0);
+            volatile_store(Self::counter_ptr(), reload);
+            volatile_store(Self::control_ptr(), control.0 | TimerControl::ENABLE_MASK);
+        }
+    }
+
+    /// Stops the timer. The counter keeps its current value.
+    fn stop(&mut self) {
+        unsafe {
+            let control = volatile_load(Self::control_ptr());
+            volatile_store(Self::control_ptr(), control & !TimerControl::ENABLE_MASK);
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        unsafe { volatile_load(Self::control_ptr()) & TimerControl::ENABLE_MASK != 0 }
+    }
+
+    /// The current value of the counter.
+    fn counter(&self) -> u16 {
+        unsafe { volatile_load(Self::counter_ptr()) }
+    }
+
+    /// Enables or disables the overflow interrupt without stopping the timer.
+    fn set_irq_enabled(&mut self, irq: bool) {
+        unsafe {
+            let control = volatile_load(Self::control_ptr()) & !TimerControl::IRQ_MASK;
+            volatile_store(Self::control_ptr(), control | ((irq as u16) << 6));
+        }
+    }
+
+    /// Starts the timer so that it overflows `hz` times per second (as closely as the prescaler
+    /// allows), using the finest prescaler that can reach that frequency.
+    fn start_frequency(&mut self, hz: u32, control: TimerControl) {
+        let hz = if hz == 0 { 1 } else { hz };
+        let prescalers = [Prescaler::_1, Prescaler::_64, Prescaler::_256, Prescaler::_1024];
+        let mut chosen = Prescaler::_1024;
+        for &p in prescalers.iter() {
+            if CLOCK_HZ / p.cycles() / hz <= 0x10000 {
+                chosen = p;
+                break;
+            }
+        }
+        let ticks = (CLOCK_HZ / chosen.cycles() / hz).max(1).min(0x10000);
+        let mut control = control;
+        control.set_prescaler(chosen);
+        self.start((0x10000 - ticks) as u16, control);
+    }
+}
+
+impl Timer for Timer0 { const INDEX: u32 = 0; }
+impl Timer for Timer1 { const INDEX: u32 = 1; }
+impl Timer for Timer2 { const INDEX: u32 = 2; }
+impl Timer for Timer3 { const INDEX: u32 = 3; }
+
+/// The timers that can clock the Direct Sound FIFOs: timer 0 and timer 1. Only implemented by
+/// their handles.
+pub trait SoundTimer: Timer + sealed::SoundTimer {
+    /// Starts the timer so that it overflows once per sample at `sample_rate` Hz. The FIFOs that
+    /// should follow this timer are selected in SOUNDCNT_H (see `Sound::set_direct_sound_control`).
+    fn start_sample_clock(&mut self, sample_rate: u32) {
+        let rate = if sample_rate == 0 { 1 } else { sample_rate };
+        let ticks = (CLOCK_HZ / rate).max(1).min(0x10000);
+        self.start((0x10000 - ticks) as u16, TimerControl::new());
+    }
+}
+
+impl SoundTimer for Timer0 {}
+impl SoundTimer for Timer1 {}